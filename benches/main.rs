@@ -1,6 +1,6 @@
 use std::{thread::sleep, time::Duration};
 
-use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
 use rand::{prelude::SliceRandom, rngs::SmallRng, SeedableRng};
 
 #[cfg(feature = "benchmark-idna")]
@@ -38,6 +38,15 @@ const DATA: &[&str] = &[
     "🦀.☕",
 ];
 
+/// Domains mixing scripts, case and mapped characters across their labels.
+const MIXED: &[&str] = &[
+    "WWW.Bücher.例子.παράδειγμα.рф",
+    "ｍüｎｃｈｅｎ。xn--fsqu00a.Example.COM",
+    "ශ්‍රී.नमस्ते.faß.βόλος.de",
+    "mail.Почему.日本語.تجربة.xn--mnchen-3ya.de",
+    "🦀.☕.exam💩ple.Straße.ǅemal.com",
+];
+
 const SEED: u64 = 0x5EED_5EED;
 
 #[cfg(feature = "benchmark-idna")]
@@ -104,5 +113,26 @@ fn to_ascii(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, to_ascii);
+fn mixed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mixed");
+    let bytes = MIXED.iter().map(|x| x.len()).sum::<usize>();
+    group.throughput(Throughput::Bytes(u64::try_from(bytes).unwrap()));
+
+    group.bench_function("to_ascii", |b| {
+        b.iter(|| {
+            for x in MIXED {
+                edna::to_ascii(black_box(x)).unwrap();
+            }
+        });
+    });
+    group.bench_function("to_unicode", |b| {
+        b.iter(|| {
+            for x in MIXED {
+                edna::to_unicode(black_box(x)).unwrap();
+            }
+        });
+    });
+}
+
+criterion_group!(benches, to_ascii, mixed);
 criterion_main!(benches);
@@ -1,8 +1,9 @@
 use std::{
+    collections::BTreeMap,
     env,
-    fs::File,
-    io::{self, BufRead, Write},
-    str::FromStr,
+    fs::{self, File},
+    io::Write,
+    ops::RangeInclusive,
 };
 
 use prettyplease::unparse;
@@ -14,23 +15,10 @@ use syn::parse_quote;
 mod mapping;
 use mapping::Mapping;
 
-impl FromStr for Mapping<'_> {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        #[expect(clippy::match_same_arms)]
-        match s {
-            "valid" => Ok(Self::Valid),
-            "ignored" => Ok(Self::Ignored),
-            "disallowed" => Ok(Self::Disallowed),
-            // we don't support transitional processing
-            "deviation" => Ok(Self::Valid),
-            e => Err(format!(
-                "no FromStr implementation for Mapping from input \'{e}\'"
-            )),
-        }
-    }
-}
+#[path = "src/parse.rs"]
+#[allow(dead_code, reason = "parts are only used by MappingTable")]
+mod parse;
+use parse::{IdnaMap, Line};
 
 impl ToTokens for Mapping<'_> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
@@ -44,126 +32,325 @@ impl ToTokens for Mapping<'_> {
     }
 }
 
-#[derive(Debug)]
-struct IdnaMap {
-    start: char,
-    end: char,
-    status: String,
-    map: String,
+fn generate_data() -> Vec<Line> {
+    let table =
+        fs::read_to_string("IdnaMappingTable.txt").expect("failed to read IdnaMappingTable.txt");
+
+    parse::read_lines(table.lines(), |a, b| {
+        println!("cargo:warning=skipping invalid char range: {a:X}..={b:X}");
+    })
+    .expect("failed to parse IdnaMappingTable.txt")
+}
+
+/// Generates the reverse mapping behind `Mapping::preimages`, from every mapping target to the
+/// ranges of code points mapped to it, sorted by target.
+///
+/// Unlike the other tables, this includes uppercase ASCII.
+///
+/// - Without `small`, `PREIMAGES` holds every target along with its ranges.
+/// - With `small`, `PREIMAGE_RANGES` holds the ranges of all targets in order, and
+///   `PREIMAGE_RANGE_ENDS` holds the end of the ranges of each target. `PREIMAGE_POOL` is the
+///   concatenation of the targets, which end at the offsets in `PREIMAGE_POOL_ENDS`.
+fn preimage_tables(lines: &[Line], small: bool) -> syn::File {
+    let mut preimages = BTreeMap::<&str, Vec<RangeInclusive<char>>>::new();
+    for x in lines
+        .iter()
+        .map(|x| &x.map)
+        .filter(|x| x.status == "mapped")
+    {
+        let ranges = preimages.entry(&x.map).or_default();
+        match ranges.last_mut() {
+            Some(last) if *last.end() as u32 + 1 == x.start as u32 => {
+                *last = *last.start()..=x.end;
+            }
+            _ => ranges.push(x.start..=x.end),
+        }
+    }
+
+    let range = |x: &RangeInclusive<char>| {
+        let (start, end) = (x.start(), x.end());
+        quote!(#start..=#end)
+    };
+
+    if !small {
+        let entries = preimages.iter().map(|(target, ranges)| {
+            let ranges = ranges.iter().map(range);
+            quote!((#target, &[#(#ranges),*]))
+        });
+
+        return parse_quote! {
+            #[allow(clippy::unicode_not_nfc)]
+            pub const PREIMAGES: &[(&str, &[core::ops::RangeInclusive<char>])] = &[#(#entries),*];
+        };
+    }
+
+    let mut pool = String::new();
+    let mut pool_ends = Vec::new();
+    let mut range_ends = Vec::new();
+    for (target, ranges) in &preimages {
+        pool.push_str(target);
+        pool_ends.push(u16::try_from(pool.len()).expect("PREIMAGE_POOL too long"));
+        let end = range_ends.last().copied().unwrap_or(0) + ranges.len();
+        range_ends.push(end);
+    }
+    let range_ends = range_ends
+        .into_iter()
+        .map(|x| u16::try_from(x).expect("too many preimage ranges"));
+    let ranges = preimages.values().flatten().map(range);
+
+    parse_quote! {
+        #[allow(clippy::unicode_not_nfc)]
+        pub const PREIMAGE_RANGES: &[core::ops::RangeInclusive<char>] = &[#(#ranges),*];
+        pub const PREIMAGE_RANGE_ENDS: &[u16] = &[#(#range_ends),*];
+        #[allow(clippy::unicode_not_nfc)]
+        pub const PREIMAGE_POOL: &str = #pool;
+        pub const PREIMAGE_POOL_ENDS: &[u16] = &[#(#pool_ends),*];
+    }
 }
 
-fn read_line(line: &str) -> Result<IdnaMap, (u32, u32)> {
-    let (line, _) = line.rsplit_once('#').unwrap();
-    let mut iter = line.split(';').map(str::trim);
+/// Generates the tables behind `char_info` from the comments of the mapping table.
+///
+/// - `AGES` holds the first code point of every run of lines with the same age, and that age.
+///   With `small`, both are packed into a `u32`, see [`age_code`].
+/// - With the `names` feature, `NAMED` holds every code point named by a comment, in order.
+///   `NAMES` is the concatenation of their names, and `NAME_ENDS` holds the end of each name.
+fn info_tables(lines: &[Line], small: bool) -> syn::File {
+    let ages = parse::ages(lines);
 
-    let (start, end);
-    if let Some(x) = iter.next() {
-        (start, end) = x.split_once("..").unwrap_or((x, x));
+    let mut tables: syn::File = if small {
+        let ages = ages
+            .iter()
+            .map(|&(start, age)| (start as u32) << 8 | u32::from(age_code(age)));
+        parse_quote! {
+            #[allow(clippy::unreadable_literal)]
+            pub const AGES: &[u32] = &[#(#ages),*];
+        }
     } else {
-        unreachable!();
+        let ages = ages.iter().map(|(start, age)| match age {
+            Some((major, minor)) => quote!((#start, Some((#major, #minor)))),
+            None => quote!((#start, None)),
+        });
+        parse_quote! {
+            #[allow(clippy::unicode_not_nfc)]
+            pub const AGES: &[(char, Option<(u8, u8)>)] = &[#(#ages),*];
+        }
+    };
+
+    if env::var_os("CARGO_FEATURE_NAMES").is_some() {
+        let mut named = Vec::new();
+        let mut names = String::new();
+        let mut name_ends = Vec::new();
+        for (c, name) in lines.iter().flat_map(|x| &x.names) {
+            named.push(*c);
+            names.push_str(name);
+            name_ends.push(u32::try_from(names.len()).expect("NAMES too long"));
+        }
+
+        tables.items.extend::<[syn::Item; 3]>([
+            parse_quote! {
+                #[allow(clippy::unicode_not_nfc)]
+                pub const NAMED: &[char] = &[#(#named),*];
+            },
+            parse_quote!(pub const NAMES: &str = #names;),
+            parse_quote! {
+                #[allow(clippy::unreadable_literal)]
+                pub const NAME_ENDS: &[u32] = &[#(#name_ends),*];
+            },
+        ]);
     }
 
-    let a = u32::from_str_radix(start, 16).unwrap();
-    let b = u32::from_str_radix(end, 16).unwrap();
+    tables
+}
+
+/// Packs an age into the low byte used by `AGES` with `small-tables`, as `major << 3 | minor`.
+///
+/// Unassigned code points have the code 0, which no version maps to.
+fn age_code(age: Option<(u8, u8)>) -> u8 {
+    age.map_or(0, |(major, minor)| {
+        assert!(
+            (1..32).contains(&major) && minor < 8,
+            "age {major}.{minor} can not be packed"
+        );
+        major << 3 | minor
+    })
+}
 
-    let start = char::from_u32(a);
-    let end = char::from_u32(b);
+/// Generates lookup tables which favour speed over size.
+fn fast_tables(data: &[IdnaMap]) -> syn::File {
+    // For every block of 256 code points, the index of the first range which ends in or after it.
+    let blocks = (0..=0x10FF_u32 + 1).map(|block| {
+        let index = data.partition_point(|x| (x.end as u32) >> 8 < block);
+        u16::try_from(index).expect("too many ranges")
+    });
 
-    let (start, end) = start.zip(end).ok_or((a, b))?;
+    let pairs = data.iter().map(|x| {
+        let status = x.mapping();
 
-    let status = iter.next().unwrap();
+        let (start, end, nfc_inert) = (x.start, x.end, x.nfc_inert);
 
-    let chars = iter.next().map_or(String::new(), |x| {
-        x.split_whitespace()
-            .map(|x| {
-                char::from_u32(u32::from_str_radix(x, 16).unwrap())
-                    .expect("invalid UTF-8 codepoint")
-            })
-            .collect::<String>()
+        quote!((#start ..= #end, #status, #nfc_inert))
     });
 
-    Ok(IdnaMap {
-        start,
-        end,
-        status: status.to_string(),
-        map: chars,
-    })
+    parse_quote! {
+        use core::ops::RangeInclusive;
+        use crate::Mapping;
+
+        #[allow(clippy::unicode_not_nfc)]
+        pub const MAPPING: &[(RangeInclusive<char>, Mapping, bool)] = &[#(#pairs),*];
+
+        pub const BLOCKS: &[u16] = &[#(#blocks),*];
+    }
 }
 
-fn merge_ranges(ranges: Vec<IdnaMap>) -> Vec<IdnaMap> {
-    let mut merged: Vec<IdnaMap> = Vec::new();
+/// Generates packed lookup tables which favour size over speed.
+///
+/// - `STARTS` holds the distance of every range start from the previous one as a LEB128 varint.
+///   Ranges are contiguous, so every range ends right before the next one starts.
+/// - `CHECKPOINTS` holds, for every `CHUNK` ranges, the start of the first one, the offset of the
+///   following delta in `STARTS`, and the number of mapped ranges before it.
+/// - `STATUS` holds a 2-bit status code per range, see [`status_code`], and `NFC_INERT` one bit
+///   per range.
+/// - `MAPPED` holds for every mapped range the index of its target in `POOL`, a string of all
+///   distinct targets which end at the offsets in `POOL_ENDS`.
+fn small_tables(data: &[IdnaMap]) -> syn::File {
+    const CHUNK: usize = 32;
+
+    for pair in data.windows(2) {
+        let (end, start) = (pair[0].end as u32, pair[1].start as u32);
+        // surrogates are not chars
+        assert!(
+            end + 1 == start || (end, start) == (0xD7FF, 0xE000),
+            "gap between {end:X} and {start:X}"
+        );
+    }
+    assert_eq!(data.last().map(|x| x.end), Some(char::MAX));
+
+    let mut starts = Vec::new();
+    let mut checkpoints = Vec::new();
+    let mut status = vec![0_u8; data.len().div_ceil(4)];
+    let mut nfc_inert = vec![0_u8; data.len().div_ceil(8)];
+    let mut mapped = Vec::new();
+    let mut pool = String::new();
+    let mut pool_ends = Vec::new();
+    let mut targets = std::collections::HashMap::new();
 
-    for range in ranges {
-        if let Some(last) = merged.last_mut() {
-            if overlaps(&range, last) {
-                last.end = range.end.max(last.end);
-                continue;
+    let mut prev = 0;
+    for (i, x) in data.iter().enumerate() {
+        let mut delta = x.start as u32 - prev;
+        prev = x.start as u32;
+        loop {
+            let byte = (delta & 0x7F) as u8;
+            delta >>= 7;
+            if delta == 0 {
+                starts.push(byte);
+                break;
             }
+            starts.push(byte | 0x80);
+        }
+
+        if i % CHUNK == 0 {
+            let offset = u16::try_from(starts.len()).expect("STARTS too long");
+            let rank = u16::try_from(mapped.len()).expect("too many mapped ranges");
+            checkpoints.push(quote!((#prev, #offset, #rank)));
+        }
+
+        status[i / 4] |= status_code(x) << (i % 4 * 2);
+        nfc_inert[i / 8] |= u8::from(x.nfc_inert) << (i % 8);
+
+        if x.status == "mapped" {
+            let target = *targets.entry(x.map.clone()).or_insert_with(|| {
+                pool.push_str(&x.map);
+                pool_ends.push(u16::try_from(pool.len()).expect("POOL too long"));
+                pool_ends.len() - 1
+            });
+            mapped.push(u16::try_from(target).expect("too many targets"));
         }
-        merged.push(range);
     }
 
-    merged
-}
+    let len = data.len();
+    let starts = proc_macro2::Literal::byte_string(&starts);
+    let status = proc_macro2::Literal::byte_string(&status);
+    let nfc_inert = proc_macro2::Literal::byte_string(&nfc_inert);
 
-fn overlaps(a: &IdnaMap, b: &IdnaMap) -> bool {
-    let next = char::from_u32(b.end as u32 + 1);
+    parse_quote! {
+        pub const CHUNK: usize = #CHUNK;
+        pub const LEN: usize = #len;
+        pub const STARTS: &[u8] = #starts;
+        #[allow(clippy::unreadable_literal)]
+        pub const CHECKPOINTS: &[(u32, u16, u16)] = &[#(#checkpoints),*];
+        pub const STATUS: &[u8] = #status;
+        pub const NFC_INERT: &[u8] = #nfc_inert;
+        pub const MAPPED: &[u16] = &[#(#mapped),*];
+        #[allow(clippy::unicode_not_nfc)]
+        pub const POOL: &str = #pool;
+        pub const POOL_ENDS: &[u16] = &[#(#pool_ends),*];
+    }
+}
 
-    next.is_some_and(|next| {
-        a.start <= next
-            && a.status == b.status
-            && if a.status == "mapped" {
-                a.map == b.map
-            } else {
-                true
-            }
-    })
+/// Returns the 2-bit status code used by [`small_tables`].
+fn status_code(x: &IdnaMap) -> u8 {
+    match x.mapping() {
+        Mapping::Valid => 0,
+        Mapping::Ignored => 1,
+        Mapping::Mapped(_) => 2,
+        Mapping::Disallowed => 3,
+        m => unreachable!("unexpected status {m:?}"),
+    }
 }
 
-fn generate_data() -> impl Iterator<Item = IdnaMap> {
-    let file = File::open("IdnaMappingTable.txt").expect("failed to open IdnaMappingTable.txt");
-    let reader = io::BufReader::new(file);
-
-    let idna_maps = reader
-        .lines()
-        .map(|x| x.unwrap().trim().to_string())
-        .filter(|x| !x.is_empty() && !x.starts_with('#'))
-        .filter_map(|x| {
-            read_line(&x)
-                .map_err(|(a, b)| {
-                    println!("cargo:warning=skipping invalid char range: {a:X}..={b:X}");
-                })
-                .ok()
-        })
-        .filter(|x| !(x.start.is_ascii() && x.end.is_ascii()))
-        .collect::<Vec<_>>();
-
-    let merged = merge_ranges(idna_maps);
-
-    merged.into_iter()
+/// Returns the Unicode version of a bundled data file.
+fn file_version(path: &str) -> (u8, u8, u8) {
+    let file = fs::read_to_string(path).unwrap_or_else(|_| panic!("failed to read {path}"));
+    parse::version(file.lines()).unwrap_or_else(|| panic!("{path} has no version header"))
 }
 
-fn main() {
-    println!("cargo:rustc-cfg=ugly_hack");
-    let data = generate_data();
-    let pairs = data.map(|x| {
-        let status = match x.status.as_str() {
-            "mapped" => Mapping::Mapped(x.map.as_str()),
-            s => Mapping::from_str(s).expect("invalid Mapping"),
-        };
+/// Checks that the mapping table and the conformance tests are for the same version of Unicode,
+/// and that the normalization data is not older.
+///
+/// Newer normalization data is fine, since the normalization of assigned characters never
+/// changes.
+fn check_versions() -> (u8, u8, u8) {
+    let show = |(a, b, c): (u8, u8, u8)| format!("{a}.{b}.{c}");
 
-        let (start, end) = (x.start, x.end);
+    let table = file_version("IdnaMappingTable.txt");
+    let tests = file_version("IdnaTestV2.txt");
+    assert!(
+        table == tests,
+        "IdnaMappingTable.txt is for Unicode {}, but IdnaTestV2.txt is for Unicode {}",
+        show(table),
+        show(tests),
+    );
 
-        quote!((#start ..= #end, #status))
-    });
+    let nfc = unicode_normalization::UNICODE_VERSION;
+    assert!(
+        nfc >= table,
+        "IdnaMappingTable.txt is for Unicode {}, but unicode-normalization only supports Unicode {}",
+        show(table),
+        show(nfc),
+    );
 
-    let tokens = parse_quote! {
-        use core::ops::RangeInclusive;
-        use crate::Mapping;
+    table
+}
 
-        #[allow(clippy::unicode_not_nfc)]
-        pub const MAPPING: &[(RangeInclusive<char>, Mapping)] = &[#(#pairs),*];
+fn main() {
+    println!("cargo:rustc-cfg=ugly_hack");
+    let (major, minor, update) = check_versions();
+    let lines = generate_data();
+    let small = env::var_os("CARGO_FEATURE_SMALL_TABLES").is_some();
+    let info = info_tables(&lines, small);
+    let preimages = preimage_tables(&lines, small);
+    let data = parse::ranges(lines);
+
+    let mut tokens = if small {
+        small_tables(&data)
+    } else {
+        fast_tables(&data)
     };
+    tokens.items.push(parse_quote! {
+        pub const UNICODE_VERSION: (u8, u8, u8) = (#major, #minor, #update);
+    });
+    tokens.items.extend(info.items);
+    tokens.items.extend(preimages.items);
 
     let pretty = unparse(&tokens);
 
@@ -5,17 +5,27 @@ use edna::*;
 fn main() {
     let mut rl = DefaultEditor::new().expect("Failed to init editor");
     while let Ok(ref line) = rl.readline(">> ") {
-        let mut chars = line.chars();
-        if let Some(c) = chars.next() {
-            if chars.next().is_none() {
-                println!("Mapping:of({c}) == {:?}", Mapping::of(c));
-            }
+        for c in line.chars() {
+            let CharInfo { mapping, age, name } = char_info(c);
+            let age = age.map_or_else(|| "unassigned".into(), |(a, b)| format!("{a}.{b}"));
+            println!(
+                "U+{:04X} {c:?} {mapping:?} (Unicode {age}) {}",
+                c as u32,
+                name.unwrap_or_default()
+            );
         }
         println!("to_ascii({line}) == {:?}", to_ascii(line));
+        println!("to_unicode({line}) == {:?}", to_unicode(line));
         println!("punycode::encode({line}) == {:?}", punycode::encode(line));
         println!("punycode::decode({line}) == {:?}", punycode::decode(line));
-        println!("idna::punycode::encode({line}) == {:?}", idna::punycode::encode_str(line));
-        println!("idna::to_unicode({line}) == {:?}", idna::domain_to_unicode(line));
+        println!(
+            "idna::punycode::encode({line}) == {:?}",
+            idna::punycode::encode_str(line)
+        );
+        println!(
+            "idna::to_unicode({line}) == {:?}",
+            idna::domain_to_unicode(line)
+        );
         rl.add_history_entry(line).expect("Failed to save history");
     }
 }
@@ -0,0 +1,190 @@
+//! Word-at-a-time scanning and lowercasing of ASCII text.
+//!
+//! Most hostnames are ASCII, so instead of decoding one character at a time these functions look
+//! at eight bytes at once using plain integer arithmetic. This works on every target without
+//! `std` or platform intrinsics, and the compiler is free to vectorize it further. The bytes that
+//! do not fill a whole word are handled by a scalar loop, and so is lowercasing once the first
+//! uppercase letter has been found.
+
+extern crate alloc;
+use alloc::string::String;
+
+use crate::unwrap;
+
+type Word = u64;
+
+const WORD: usize = size_of::<Word>();
+const BLOCK: usize = 4 * WORD;
+
+/// `0x01` in every byte.
+const LO: Word = Word::MAX / 0xFF;
+/// `0x80` in every byte.
+const HI: Word = LO * 0x80;
+
+/// Reads a word from exactly [`WORD`] bytes, with the first byte in the lowest bits.
+#[inline]
+fn load(chunk: &[u8]) -> Word {
+    // SAFETY: Only called on chunks_exact(WORD)
+    Word::from_le_bytes(unwrap!(chunk.try_into().ok()))
+}
+
+/// Sets the high bit of every byte of `w` which is not ASCII.
+#[inline]
+const fn non_ascii(w: Word) -> Word {
+    w & HI
+}
+
+/// Sets the high bit of every byte of `w` which is an ASCII uppercase letter, and clears all other
+/// bits.
+#[inline]
+const fn uppercase(w: Word) -> Word {
+    // Without the high bits, adding less than 0x80 to a byte never carries into the next one.
+    let low = w & !HI;
+    let at_least_a = low + LO * (0x80 - b'A' as Word);
+    let above_z = low + LO * (0x80 - b'Z' as Word - 1);
+
+    at_least_a & !above_z & !w & HI
+}
+
+/// Returns the index of the first byte matched by `word` and `byte`, or the length of `s`.
+///
+/// `word` must set the high bit of exactly those bytes which `byte` matches.
+#[inline]
+fn position(s: &[u8], word: impl Fn(Word) -> Word, byte: impl Fn(u8) -> bool) -> usize {
+    // Skip blocks of several words without a match first, which can be vectorized.
+    let mut i = 0;
+    for block in s.chunks_exact(BLOCK) {
+        let matches = block
+            .chunks_exact(WORD)
+            .fold(0, |acc, x| acc | word(load(x)));
+        if matches != 0 {
+            break;
+        }
+        i += BLOCK;
+    }
+
+    let mut chunks = s[i..].chunks_exact(WORD);
+
+    for chunk in &mut chunks {
+        let matches = word(load(chunk));
+        if matches != 0 {
+            return i + matches.trailing_zeros() as usize / 8;
+        }
+        i += WORD;
+    }
+
+    let rest = chunks.remainder();
+    i + rest.iter().position(|&b| byte(b)).unwrap_or(rest.len())
+}
+
+/// Returns the length of the longest prefix of `s` which is ASCII.
+#[must_use]
+pub fn ascii_len(s: &[u8]) -> usize {
+    position(s, non_ascii, |b| !b.is_ascii())
+}
+
+/// Returns the length of the longest prefix of `s` which is ASCII and contains no uppercase
+/// letters, i.e. which the UTS #46 mapping leaves unchanged.
+#[must_use]
+pub fn lowercase_len(s: &[u8]) -> usize {
+    position(
+        s,
+        |w| non_ascii(w) | uppercase(w),
+        |b| !b.is_ascii() || b.is_ascii_uppercase(),
+    )
+}
+
+/// Returns the index of the first ASCII uppercase letter in `s`.
+fn uppercase_position(s: &[u8]) -> Option<usize> {
+    Some(position(s, uppercase, |b| b.is_ascii_uppercase())).filter(|&i| i < s.len())
+}
+
+/// Returns `true` if `s` contains an ASCII uppercase letter.
+#[must_use]
+pub fn has_uppercase(s: &[u8]) -> bool {
+    let chunks = s.chunks_exact(WORD);
+    let rest = chunks.remainder();
+
+    // No early exit, so that the loop can be vectorized.
+    chunks.fold(0, |acc, chunk| acc | uppercase(load(chunk))) != 0
+        || rest.iter().any(u8::is_ascii_uppercase)
+}
+
+/// Converts all ASCII uppercase letters in `s` to lowercase, leaving all other characters alone.
+pub fn make_lowercase_str(s: &mut str) {
+    // Hostnames are usually lowercase already, so look for the first uppercase letter before
+    // writing anything.
+    let Some(i) = uppercase_position(s.as_bytes()) else {
+        return;
+    };
+    s[i..].make_ascii_lowercase();
+}
+
+/// Returns a copy of `s` with all ASCII uppercase letters converted to lowercase.
+#[must_use]
+pub fn to_lowercase(s: &str) -> String {
+    let mut s = String::from(s);
+    make_lowercase_str(&mut s);
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::vec::Vec;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    /// Every byte value at every offset of a word, with something both before and after.
+    fn inputs() -> impl Iterator<Item = Vec<u8>> {
+        (0..=u8::MAX).flat_map(|b| {
+            (0..2 * WORD + 3).map(move |i| {
+                let mut v = b"abcdefghijklmnopqrstuvw".to_vec();
+                v[i] = b;
+                v
+            })
+        })
+    }
+
+    #[test]
+    fn test_exhaustive() {
+        for v in inputs() {
+            let len = v.len();
+            assert_eq!(
+                ascii_len(&v),
+                v.iter().position(|b| !b.is_ascii()).unwrap_or(len)
+            );
+            assert_eq!(
+                lowercase_len(&v),
+                v.iter()
+                    .position(|b| !b.is_ascii() || b.is_ascii_uppercase())
+                    .unwrap_or(len)
+            );
+            assert_eq!(has_uppercase(&v), v.iter().any(u8::is_ascii_uppercase));
+            if let Ok(s) = core::str::from_utf8(&v) {
+                assert_eq!(to_lowercase(s), s.to_ascii_lowercase());
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::empty("", 0, 0)]
+    #[case::lowercase("www.example.com", 15, 15)]
+    #[case::uppercase("www.Example.COM", 15, 4)]
+    #[case::unicode("www.münchen.de", 5, 5)]
+    #[case::long("abcdefghijklmnopqrstuvwxyz.Ü", 27, 27)]
+    fn test_len(#[case] input: &str, #[case] ascii: usize, #[case] lowercase: usize) {
+        assert_eq!(ascii_len(input.as_bytes()), ascii);
+        assert_eq!(lowercase_len(input.as_bytes()), lowercase);
+    }
+
+    #[rstest]
+    #[case::ascii("WWW.Example.COM")]
+    #[case::unicode("ÜBER.Straße.DE")]
+    fn test_to_lowercase(#[case] input: &str) {
+        assert_eq!(to_lowercase(input), input.to_ascii_lowercase());
+    }
+}
@@ -0,0 +1,170 @@
+//! Conversion of many domains at once.
+//!
+//! The functions in this module convert every input independently and return the results in the
+//! same order. An error only affects the result of its own input. Buffers are reused from one input
+//! to the next, so a batch allocates little more than the converted domains themselves.
+//!
+//! With the `rayon` feature, [`par_to_ascii`] and [`par_to_unicode`] spread the work across all
+//! threads of the global rayon pool.
+
+extern crate alloc;
+use alloc::{borrow::Cow, vec::Vec};
+
+use crate::{pipeline::Scratch, Processor, ToAsciiError, ToUnicodeError};
+
+/// Converts every domain in `inputs` with [`crate::to_ascii`].
+///
+/// # Examples
+///
+/// ```
+/// let results = edna::batch::to_ascii(["münchen.de", "\u{10FFF}", "example.com"]);
+/// assert_eq!(results[0].as_deref(), Ok("xn--mnchen-3ya.de"));
+/// assert!(results[1].is_err());
+/// assert_eq!(results[2].as_deref(), Ok("example.com"));
+/// ```
+pub fn to_ascii<'a, I, S>(inputs: I) -> Vec<Result<Cow<'a, str>, ToAsciiError>>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + ?Sized + 'a,
+{
+    let mut scratch = Scratch::default();
+    inputs
+        .into_iter()
+        .map(|x| Processor::new().to_ascii_with(x.as_ref(), &mut scratch))
+        .collect()
+}
+
+/// Converts every domain in `inputs` with [`crate::to_unicode`].
+///
+/// # Examples
+///
+/// ```
+/// let results = edna::batch::to_unicode(["xn--mnchen-3ya.de", "xn--a.de"]);
+/// assert_eq!(results[0].as_deref(), Ok("münchen.de"));
+/// assert!(results[1].is_err());
+/// ```
+pub fn to_unicode<'a, I, S>(inputs: I) -> Vec<Result<Cow<'a, str>, ToUnicodeError>>
+where
+    I: IntoIterator<Item = &'a S>,
+    S: AsRef<str> + ?Sized + 'a,
+{
+    let mut scratch = Scratch::default();
+    inputs
+        .into_iter()
+        .map(|x| Processor::new().to_unicode_with(x.as_ref(), &mut scratch))
+        .collect()
+}
+
+/// Like [`to_ascii`], but converts the domains in parallel.
+///
+/// # Examples
+///
+/// ```
+/// let inputs = vec!["münchen.de".to_string(); 1000];
+/// let results = edna::batch::par_to_ascii(&inputs);
+/// assert!(results.iter().all(|x| x.as_deref() == Ok("xn--mnchen-3ya.de")));
+/// ```
+#[cfg(feature = "rayon")]
+pub fn par_to_ascii<S>(inputs: &[S]) -> Vec<Result<Cow<'_, str>, ToAsciiError>>
+where
+    S: AsRef<str> + Sync,
+{
+    use rayon::prelude::*;
+
+    inputs
+        .par_iter()
+        .map_init(Scratch::default, |scratch, x| {
+            Processor::new().to_ascii_with(x.as_ref(), scratch)
+        })
+        .collect()
+}
+
+/// Like [`to_unicode`], but converts the domains in parallel.
+#[cfg(feature = "rayon")]
+pub fn par_to_unicode<S>(inputs: &[S]) -> Vec<Result<Cow<'_, str>, ToUnicodeError>>
+where
+    S: AsRef<str> + Sync,
+{
+    use rayon::prelude::*;
+
+    inputs
+        .par_iter()
+        .map_init(Scratch::default, |scratch, x| {
+            Processor::new().to_unicode_with(x.as_ref(), scratch)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::string::String;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::empty(&[], &[])]
+    #[case::mixed(
+        &["www.example.com", "WWW.Example.COM", "münchen.de", "\u{10FFF}", "xn--a.de", "🦀.☕"],
+        &[
+            Ok("www.example.com"),
+            Ok("www.example.com"),
+            Ok("xn--mnchen-3ya.de"),
+            Err(ToAsciiError::InvalidCharacter(Some('\u{10FFF}'))),
+            Ok("xn--a.de"),
+            Ok("xn--zs9h.xn--53h"),
+        ]
+    )]
+    fn test_to_ascii(#[case] inputs: &[&str], #[case] expected: &[Result<&str, ToAsciiError>]) {
+        let got = to_ascii(inputs);
+        let deref = got
+            .iter()
+            .map(|x| x.as_deref().map_err(Clone::clone))
+            .collect::<Vec<_>>();
+        assert_eq!(deref, expected);
+
+        let owned = inputs.iter().map(|&x| String::from(x)).collect::<Vec<_>>();
+        assert_eq!(to_ascii(&owned), got);
+
+        #[cfg(feature = "rayon")]
+        assert_eq!(par_to_ascii(inputs), got);
+    }
+
+    #[rstest]
+    #[case::mixed(
+        &["xn--mnchen-3ya.de", "xn--a.de", "Bücher.de", "\u{10FFF}"],
+        &[
+            Ok("münchen.de"),
+            Err(ToUnicodeError::InvalidPunycode("xn--a".into())),
+            Ok("bücher.de"),
+            Err(ToUnicodeError::InvalidCharacter(Some('\u{10FFF}'))),
+        ]
+    )]
+    fn test_to_unicode(#[case] inputs: &[&str], #[case] expected: &[Result<&str, ToUnicodeError>]) {
+        let got = to_unicode(inputs);
+        let deref = got
+            .iter()
+            .map(|x| x.as_deref().map_err(Clone::clone))
+            .collect::<Vec<_>>();
+        assert_eq!(deref, expected);
+
+        #[cfg(feature = "rayon")]
+        assert_eq!(par_to_unicode(inputs), got);
+    }
+
+    #[test]
+    fn test_lines() {
+        let zone = "a.de\nB.DE\nc.de";
+        let got = to_ascii(zone.lines());
+        let deref = got
+            .iter()
+            .map(|x| x.as_deref().map_err(Clone::clone))
+            .collect::<Vec<_>>();
+        assert_eq!(deref, [Ok("a.de"), Ok("b.de"), Ok("c.de")]);
+        assert!(matches!(got[0], Ok(Cow::Borrowed(_))));
+        assert!(matches!(got[1], Ok(Cow::Owned(_))));
+    }
+}
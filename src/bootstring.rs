@@ -0,0 +1,674 @@
+//! A generic implementation of the Bootstring algorithm from RFC 3492.
+//!
+//! Punycode is one instance of Bootstring, see [`crate::punycode::PUNYCODE`]. Other instances can
+//! be built by choosing different [`Parameters`] and a digit alphabet.
+
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+use thiserror::Error;
+
+use crate::{_assert, unwrap};
+
+type Utf32 = Vec<char>;
+
+const INVALID_DIGIT: u8 = u8::MAX;
+
+/// The numeric parameters of a Bootstring instance.
+///
+/// See RFC 3492 section 5 for their meaning. `base` is not included, since it is always the length
+/// of the digit alphabet.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Parameters {
+    /// The lower bound of the digit threshold, which applies to the first digits of a number.
+    ///
+    /// Must be at least 1 and at most `t_max`.
+    pub t_min: u32,
+    /// The upper bound of the digit threshold, which applies to its last digits.
+    ///
+    /// Must be less than `base`.
+    pub t_max: u32,
+    /// Used when adapting the bias after each delta. Larger values let the bias grow more slowly.
+    ///
+    /// Must be at least 1.
+    pub skew: u32,
+    /// The first delta is divided by this when adapting the bias, rather than by 2 as the later
+    /// ones, since it is usually much larger.
+    ///
+    /// Must be at least 2.
+    pub damp: u32,
+    /// The bias used for the first delta.
+    ///
+    /// `initial_bias % base` must be at most `base - t_min`.
+    pub initial_bias: u32,
+    /// The first code point which is not basic. Code points below it are copied as they are.
+    pub initial_n: u32,
+}
+
+/// A Bootstring encoder and decoder.
+///
+/// Code points below `initial_n` are basic and are copied to the output as-is. All other code
+/// points are encoded using the digit alphabet, after the delimiter.
+///
+/// ASCII letters in the alphabet are decoded case-insensitively, unless both cases of a letter are
+/// part of the alphabet.
+///
+/// # Examples
+///
+/// ```
+/// use edna::bootstring::{Bootstring, Parameters};
+///
+/// // A base-32 variant that avoids characters which are awkward in file names.
+/// const BASE32: Bootstring = Bootstring::new(
+///     Parameters {
+///         t_min: 1,
+///         t_max: 26,
+///         skew: 38,
+///         damp: 700,
+///         initial_bias: 72,
+///         initial_n: 128,
+///     },
+///     b"abcdefghijklmnopqrstuvwxyz234567",
+///     '_',
+/// );
+///
+/// let encoded = BASE32.encode("München").unwrap();
+/// assert_eq!(BASE32.decode(&encoded).as_deref(), Ok("München"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bootstring {
+    params: Parameters,
+    base: u32,
+    delimiter: char,
+    alphabet: &'static [u8],
+    digits: [u8; 128],
+}
+
+#[expect(
+    clippy::inline_always,
+    reason = "instances are constants, inlining the hot helpers lets their parameters be folded in"
+)]
+impl Bootstring {
+    /// Creates a new Bootstring instance.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the parameters are invalid. When used to initialize a `const`, this is a compile
+    /// time error. The following must hold:
+    ///
+    /// - `alphabet` contains between 2 and 128 unique ASCII bytes.
+    /// - `delimiter` is ASCII and not part of `alphabet`.
+    /// - `1 <= t_min <= t_max <= base - 1`
+    /// - `skew >= 1`
+    /// - `damp >= 2`
+    /// - `initial_bias % base <= base - t_min`
+    #[must_use]
+    #[expect(clippy::cast_possible_truncation)]
+    pub const fn new(params: Parameters, alphabet: &'static [u8], delimiter: char) -> Self {
+        assert!(
+            alphabet.len() >= 2 && alphabet.len() <= 128,
+            "alphabet must contain between 2 and 128 digits"
+        );
+        assert!(delimiter.is_ascii(), "delimiter must be ASCII");
+
+        let base = alphabet.len() as u32;
+        let mut digits = [INVALID_DIGIT; 128];
+
+        let mut i = 0;
+        while i < alphabet.len() {
+            let b = alphabet[i];
+            assert!(b.is_ascii(), "alphabet must be ASCII");
+            assert!(
+                b != delimiter as u8,
+                "delimiter must not be part of alphabet"
+            );
+            assert!(
+                digits[b as usize] == INVALID_DIGIT,
+                "alphabet must not contain duplicates"
+            );
+            digits[b as usize] = i as u8;
+            i += 1;
+        }
+
+        // fold case for letters which only appear in one case
+        let mut i = 0;
+        while i < alphabet.len() {
+            let b = alphabet[i];
+            let other = if b.is_ascii_lowercase() {
+                b.to_ascii_uppercase()
+            } else {
+                b.to_ascii_lowercase()
+            };
+            if other != b && digits[other as usize] == INVALID_DIGIT {
+                digits[other as usize] = i as u8;
+            }
+            i += 1;
+        }
+
+        assert!(
+            params.t_min >= 1 && params.t_min <= params.t_max && params.t_max < base,
+            "1 <= t_min <= t_max <= base - 1 must hold"
+        );
+        assert!(params.skew >= 1, "skew must be at least 1");
+        assert!(params.damp >= 2, "damp must be at least 2");
+        assert!(
+            params.initial_bias % base <= base - params.t_min,
+            "initial_bias % base <= base - t_min must hold"
+        );
+
+        Self {
+            params,
+            base,
+            delimiter,
+            alphabet,
+            digits,
+        }
+    }
+
+    /// Returns the numeric parameters of this instance.
+    #[must_use]
+    pub const fn parameters(&self) -> Parameters {
+        self.params
+    }
+
+    /// Returns the number of digits in the alphabet.
+    #[must_use]
+    pub const fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// Returns the delimiter between basic and encoded code points.
+    #[must_use]
+    pub const fn delimiter(&self) -> char {
+        self.delimiter
+    }
+
+    /// Returns the digit alphabet.
+    #[must_use]
+    pub const fn alphabet(&self) -> &'static [u8] {
+        self.alphabet
+    }
+
+    /// Encodes `input` using this instance.
+    ///
+    /// # Errors
+    ///
+    /// Overflow has occured.
+    #[inline]
+    pub fn encode(&self, input: &str) -> Result<String, EncodeError> {
+        let mut chars = Utf32::with_capacity(input.len());
+        chars.extend(input.chars());
+        let mut output = String::with_capacity(input.len() * 4);
+        self.encode_into(&chars, &mut output)?;
+        Ok(output)
+    }
+
+    /// Encodes `input` using this instance, appending the result to `output`.
+    ///
+    /// # Errors
+    ///
+    /// Overflow has occured. `output` may contain partial output.
+    #[inline]
+    pub fn encode_into(&self, input: &[char], output: &mut String) -> Result<(), EncodeError> {
+        self.encode_with(input, output, &mut Utf32::with_capacity(input.len()))
+    }
+
+    /// Like [`Bootstring::encode_into`], but reuses `non_basic` as scratch space.
+    #[inline(always)]
+    #[expect(clippy::cast_possible_truncation)]
+    pub(crate) fn encode_with(
+        &self,
+        input: &[char],
+        output: &mut String,
+        non_basic: &mut Utf32,
+    ) -> Result<(), EncodeError> {
+        let Parameters {
+            t_min,
+            t_max,
+            initial_bias,
+            initial_n,
+            ..
+        } = self.params;
+        let base = self.base;
+
+        non_basic.clear();
+        let mut basic_len = 0;
+
+        for &c in input {
+            if (c as u32) < initial_n {
+                output.push(c);
+                basic_len += 1;
+            } else {
+                non_basic.push(c);
+            }
+        }
+
+        non_basic.sort_unstable();
+        non_basic.dedup();
+        let mut non_basic = non_basic.iter().copied();
+
+        if basic_len > 0 {
+            output.push(self.delimiter);
+        }
+
+        let mut cp = initial_n;
+        let mut delta = 0;
+        let mut bias = initial_bias;
+        let mut processed = basic_len;
+
+        while processed < u32::try_from(input.len()).map_err(|_| EncodeError::Overflow)? {
+            // SAFETY: input always contains a code point >= cp while processed < input.len()
+            let min_cp = unwrap!(non_basic.next()) as u32;
+            delta = (min_cp - cp)
+                .checked_mul(processed + 1)
+                .and_then(|x| x.checked_add(delta))
+                .ok_or(EncodeError::Overflow)?;
+            cp = min_cp;
+
+            // Counting smaller code points separately from finding the next occurence of `cp`
+            // lets the compiler vectorize the hot loop.
+            let less = |x: &[char]| x.iter().filter(|&&c| (c as u32) < cp).count() as u32;
+            let mut rest = input;
+            while let Some(i) = rest.iter().position(|&c| c as u32 == cp) {
+                delta = delta
+                    .checked_add(less(&rest[..i]))
+                    .ok_or(EncodeError::Overflow)?;
+                rest = &rest[i + 1..];
+
+                let mut q = delta;
+                for k in (base..).step_by(base as usize) {
+                    let t = self.clamped_sub(k, bias);
+                    // mutants test for clamped_sub
+                    // SAFETY: clamped to t_min ..= t_max
+                    _assert!((t_min..=t_max).contains(&t));
+
+                    if q < t {
+                        break;
+                    }
+
+                    let value = t + ((q - t) % (base - t));
+                    output.push(self.encode_digit(value));
+                    q = (q - t) / (base - t);
+                }
+                output.push(self.encode_digit(q));
+                bias = self.adapt(delta, processed + 1, processed == basic_len);
+                delta = 0;
+                processed += 1;
+            }
+            delta = delta
+                .checked_add(less(rest) + 1)
+                .ok_or(EncodeError::Overflow)?;
+            cp += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Decodes `input` using this instance.
+    ///
+    /// # Errors
+    ///
+    /// - Overflow has occured.
+    /// - `input` contains a character which is not a digit.
+    /// - An invalid sequence was encountered.
+    #[inline]
+    pub fn decode(&self, input: &str) -> Result<String, DecodeError> {
+        let (basic, encoded) = input
+            .rsplit_once(self.delimiter)
+            .map_or(("", input), |(l, r)| (l, r));
+
+        self.decode_chars(basic.chars(), encoded.chars())
+            .map(|x| x.into_iter().collect())
+    }
+
+    /// Encodes UTF-32 `input` using this instance.
+    ///
+    /// # Errors
+    ///
+    /// Overflow has occured.
+    #[inline]
+    pub fn encode_utf32(&self, input: &[char]) -> Result<Utf32, EncodeError> {
+        let mut output = String::with_capacity(input.len() * 4);
+        self.encode_into(input, &mut output)?;
+        Ok(output.chars().collect())
+    }
+
+    /// Encodes UTF-16 `input` using this instance.
+    ///
+    /// # Errors
+    ///
+    /// - `input` contains an unpaired surrogate.
+    /// - Overflow has occured.
+    #[inline]
+    pub fn encode_utf16(&self, input: &[u16]) -> Result<Vec<u16>, EncodeError> {
+        let input = char::decode_utf16(input.iter().copied())
+            .collect::<Result<Utf32, _>>()
+            .map_err(|e| EncodeError::UnpairedSurrogate(e.unpaired_surrogate()))?;
+        let mut output = String::with_capacity(input.len() * 4);
+        self.encode_into(&input, &mut output)?;
+        Ok(output.encode_utf16().collect())
+    }
+
+    /// Decodes UTF-32 `input` using this instance.
+    ///
+    /// # Errors
+    ///
+    /// See [`Bootstring::decode`].
+    #[inline]
+    pub fn decode_utf32(&self, input: &[char]) -> Result<Utf32, DecodeError> {
+        let (basic, encoded) = input
+            .iter()
+            .rposition(|&c| c == self.delimiter)
+            .map_or((&[][..], input), |i| (&input[..i], &input[i + 1..]));
+
+        self.decode_chars(basic.iter().copied(), encoded.iter().copied())
+    }
+
+    /// Decodes UTF-16 `input` using this instance.
+    ///
+    /// # Errors
+    ///
+    /// - `input` contains an unpaired surrogate.
+    /// - See [`Bootstring::decode`].
+    #[inline]
+    pub fn decode_utf16(&self, input: &[u16]) -> Result<Vec<u16>, DecodeError> {
+        let input = char::decode_utf16(input.iter().copied())
+            .collect::<Result<Utf32, _>>()
+            .map_err(|e| DecodeError::UnpairedSurrogate(e.unpaired_surrogate()))?;
+        let output = self.decode_utf32(&input)?;
+
+        let mut buf = [0; 2];
+        let mut ret = Vec::with_capacity(output.len());
+        for c in output {
+            ret.extend_from_slice(c.encode_utf16(&mut buf));
+        }
+
+        Ok(ret)
+    }
+
+    /// Decodes `input` bytes using this instance.
+    ///
+    /// # Errors
+    ///
+    /// - `input` is not ASCII.
+    /// - See [`Bootstring::decode`].
+    #[inline]
+    pub fn decode_bytes(&self, input: &[u8]) -> Result<String, DecodeError> {
+        if !input.is_ascii() {
+            return Err(DecodeError::NonAscii);
+        }
+
+        let (basic, encoded) = input
+            .iter()
+            .rposition(|&b| b == self.delimiter as u8)
+            .map_or((&[][..], input), |i| (&input[..i], &input[i + 1..]));
+
+        self.decode_chars(
+            basic.iter().copied().map(char::from),
+            encoded.iter().copied().map(char::from),
+        )
+        .map(|x| x.into_iter().collect())
+    }
+
+    /// Decodes the basic code points `basic` followed by the digits `encoded`.
+    ///
+    /// `basic` and `encoded` are the parts before and after the last delimiter.
+    #[inline(always)]
+    #[expect(clippy::cast_possible_truncation)]
+    pub(crate) fn decode_chars(
+        &self,
+        basic: impl Iterator<Item = char>,
+        mut encoded: impl Iterator<Item = char>,
+    ) -> Result<Utf32, DecodeError> {
+        let Parameters {
+            t_min,
+            t_max,
+            initial_bias,
+            initial_n,
+            ..
+        } = self.params;
+        let base = self.base;
+
+        let mut output = basic.collect::<Utf32>();
+
+        let mut cp = initial_n;
+        let mut i: u32 = 0;
+        let mut bias = initial_bias;
+
+        while let Some(mut byte) = encoded.next() {
+            let old_i = i;
+            let mut weight = 1;
+
+            for k in (base..).step_by(base as usize) {
+                let digit = self.decode_digit(byte).ok_or(DecodeError::NonAscii)?;
+
+                let product = digit.checked_mul(weight).ok_or(DecodeError::Overflow)?;
+                i = i.checked_add(product).ok_or(DecodeError::Overflow)?;
+
+                let t = self.clamped_sub(k, bias);
+                // mutants test for clamped_sub
+                // SAFETY: clamped to t_min ..= t_max
+                _assert!((t_min..=t_max).contains(&t));
+
+                if digit < t {
+                    break;
+                }
+
+                weight = weight.checked_mul(base - t).ok_or(DecodeError::Overflow)?;
+                byte = encoded.next().ok_or(DecodeError::InvalidSequence)?;
+            }
+            bias = self.adapt(i - old_i, output.len() as u32 + 1, old_i == 0);
+            cp = cp
+                .checked_add(i / (output.len() as u32 + 1))
+                .ok_or(DecodeError::Overflow)?;
+            i %= output.len() as u32 + 1;
+            let c = char::from_u32(cp).ok_or(DecodeError::InvalidSequence)?;
+            output.insert(i as usize, c);
+            i += 1;
+        }
+
+        Ok(output)
+    }
+
+    /// Decodes `input` using this instance without input validation.
+    ///
+    /// # Safety
+    ///
+    /// See [`crate::punycode::decode_unchecked`].
+    #[cfg(not(feature = "forbid-unsafe"))]
+    #[expect(clippy::cast_possible_truncation)]
+    #[must_use]
+    pub unsafe fn decode_unchecked(&self, input: &str) -> String {
+        let Parameters {
+            initial_bias,
+            initial_n,
+            ..
+        } = self.params;
+        let base = self.base;
+
+        let (mut output, mut encoded) = input.rsplit_once(self.delimiter).map_or_else(
+            || (Utf32::new(), input.chars()),
+            |(l, r)| (l.chars().collect::<Utf32>(), r.chars()),
+        );
+
+        let mut cp = initial_n;
+        let mut i: u32 = 0;
+        let mut bias = initial_bias;
+
+        while let Some(mut byte) = encoded.next() {
+            let old_i = i;
+            let mut weight = 1;
+
+            for k in (base..).step_by(base as usize) {
+                // SAFETY: Caller guarantees valid input.
+                let digit = unsafe { self.decode_digit(byte).unwrap_unchecked() };
+
+                i += digit * weight;
+
+                let t = self.clamped_sub(k, bias);
+
+                if digit < t {
+                    break;
+                }
+
+                weight *= base - t;
+                // SAFETY: Caller guarantees valid input.
+                byte = unsafe { encoded.next().unwrap_unchecked() };
+            }
+            bias = self.adapt(i - old_i, output.len() as u32 + 1, old_i == 0);
+            cp += i / (output.len() as u32 + 1);
+            i %= output.len() as u32 + 1;
+            // SAFETY: Caller guarantees valid input.
+            let c = unsafe { char::from_u32_unchecked(cp) };
+            output.insert(i as usize, c);
+            i += 1;
+        }
+
+        output.into_iter().collect::<String>()
+    }
+
+    #[must_use]
+    #[inline(always)]
+    const fn adapt(&self, mut delta: u32, num_points: u32, first_time: bool) -> u32 {
+        let Parameters {
+            t_min,
+            t_max,
+            skew,
+            damp,
+            ..
+        } = self.params;
+        let base = self.base;
+
+        // SAFETY: num_points (processed + 1) is always > 0, even on an empty string.
+        _assert!(num_points > 0);
+
+        delta /= if first_time { damp } else { 2 };
+        delta += delta / num_points;
+        let mut k = 0;
+        while delta > (base - t_min) * t_max / 2 {
+            delta /= base - t_min;
+            k += base;
+        }
+        k + (base - t_min + 1) * delta / (delta + skew)
+    }
+
+    #[must_use]
+    #[inline(always)]
+    const fn clamped_sub(&self, k: u32, bias: u32) -> u32 {
+        if k <= bias {
+            self.params.t_min
+        } else if k >= bias + self.params.t_max {
+            self.params.t_max
+        } else {
+            k - bias
+        }
+    }
+
+    #[must_use]
+    #[inline(always)]
+    fn decode_digit(&self, c: char) -> Option<u32> {
+        self.digits
+            .get(c as usize)
+            .copied()
+            .filter(|&d| d != INVALID_DIGIT)
+            .map(u32::from)
+    }
+
+    #[must_use]
+    #[inline(always)]
+    fn encode_digit(&self, d: u32) -> char {
+        // SAFETY: d is always < base in encode_into()
+        char::from(*unwrap!(self.alphabet.get(d as usize)))
+    }
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum EncodeError {
+    #[error("overflow")]
+    Overflow,
+    #[error("unpaired surrogate: {0:#06X}")]
+    UnpairedSurrogate(u16),
+}
+
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum DecodeError {
+    #[error("invalid bootstring sequence")]
+    InvalidSequence,
+    #[error("input should be ASCII bootstring")]
+    NonAscii,
+    #[error("overflow")]
+    Overflow,
+    #[error("unpaired surrogate: {0:#06X}")]
+    UnpairedSurrogate(u16),
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    const PARAMS: Parameters = Parameters {
+        t_min: 1,
+        t_max: 26,
+        skew: 38,
+        damp: 700,
+        initial_bias: 72,
+        initial_n: 128,
+    };
+
+    const BASE32: Bootstring = Bootstring::new(PARAMS, b"abcdefghijklmnopqrstuvwxyz234567", '_');
+
+    #[rstest]
+    #[case::ascii("example")]
+    #[case::munchen("München")]
+    #[case::japanese("なぜみんな日本語を話してくれないのか")]
+    #[case::emoji("🦀")]
+    #[case::empty("")]
+    fn roundtrip_base32(#[case] input: &str) {
+        let encoded = BASE32.encode(input).unwrap();
+        assert_eq!(BASE32.decode(&encoded).as_deref(), Ok(input));
+        assert_eq!(
+            BASE32
+                .decode(&encoded.to_uppercase())
+                .map(|x| x.to_lowercase()),
+            Ok(input.to_lowercase())
+        );
+    }
+
+    #[test]
+    fn case_sensitive_alphabet() {
+        const MIXED: Bootstring =
+            Bootstring::new(PARAMS, b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJ", '-');
+        let encoded = MIXED.encode("München").unwrap();
+        assert_eq!(MIXED.decode(&encoded).as_deref(), Ok("München"));
+        assert_ne!(
+            MIXED.decode(&encoded.to_uppercase()).as_deref(),
+            Ok("München")
+        );
+    }
+
+    #[rstest]
+    #[case::duplicate(PARAMS, b"aab", '-')]
+    #[case::delimiter(PARAMS, b"ab-", '-')]
+    #[case::short(PARAMS, b"a", '-')]
+    #[case::t_min(Parameters { t_min: 0, ..PARAMS }, b"abcdefghijklmnopqrstuvwxyz0123456789", '-')]
+    #[case::t_max(Parameters { t_max: 36, ..PARAMS }, b"abcdefghijklmnopqrstuvwxyz0123456789", '-')]
+    #[case::damp(Parameters { damp: 1, ..PARAMS }, b"abcdefghijklmnopqrstuvwxyz0123456789", '-')]
+    #[should_panic(expected = "must")]
+    fn invalid_parameters(
+        #[case] params: Parameters,
+        #[case] alphabet: &'static [u8],
+        #[case] delimiter: char,
+    ) {
+        let _ = Bootstring::new(params, alphabet, delimiter);
+    }
+
+    #[test]
+    fn encode_overflow() {
+        let input = "a".repeat(4096) + "\u{FFF80}";
+        assert_eq!(BASE32.encode(&input), Err(EncodeError::Overflow));
+    }
+}
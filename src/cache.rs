@@ -0,0 +1,419 @@
+//! Caches for the results of [`to_ascii`] and [`to_unicode`].
+//!
+//! Converting the same hostnames over and over repeats mapping, normalization and Punycode
+//! encoding every time. [`Cache`] remembers a bounded number of recent results per direction, so
+//! that a repeated lookup only costs one hash. [`SyncCache`] is the same cache behind a lock, for
+//! sharing between threads.
+//!
+//! Both use the clock algorithm, an approximation of least recently used eviction: every entry has
+//! a reference bit which is set on a hit. When the cache is full, a hand sweeps over the entries,
+//! clearing reference bits until it finds an entry which has not been used since the last sweep,
+//! and replaces that.
+
+extern crate std;
+
+use alloc::{boxed::Box, sync::Arc, vec::Vec};
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::{to_ascii, to_unicode, ToAsciiError, ToUnicodeError};
+
+/// The result of a cached conversion.
+pub type CacheResult<E = ToAsciiError> = Result<Arc<str>, E>;
+
+/// Hit and miss counts of a cache.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CacheStats {
+    /// Lookups which were answered from the cache.
+    pub hits: u64,
+    /// Lookups which required a conversion.
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Returns the fraction of lookups which were answered from the cache.
+    ///
+    /// Returns 0 if there have not been any lookups.
+    #[must_use]
+    #[expect(clippy::cast_precision_loss)]
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Slot<E> {
+    key: Box<str>,
+    value: CacheResult<E>,
+    referenced: bool,
+}
+
+/// A clock cache for a single direction.
+#[derive(Debug, Clone)]
+struct Clock<E> {
+    index: HashMap<Box<str>, usize>,
+    slots: Vec<Slot<E>>,
+    hand: usize,
+}
+
+impl<E: Clone> Clock<E> {
+    fn new() -> Self {
+        Self {
+            index: HashMap::new(),
+            slots: Vec::new(),
+            hand: 0,
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<CacheResult<E>> {
+        let slot = &mut self.slots[*self.index.get(key)?];
+        slot.referenced = true;
+        Some(slot.value.clone())
+    }
+
+    fn insert(&mut self, key: &str, value: CacheResult<E>, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+
+        // Another thread may have inserted the same key while this one was converting.
+        if let Some(&i) = self.index.get(key) {
+            self.slots[i].value = value;
+            return;
+        }
+
+        let slot = Slot {
+            key: key.into(),
+            value,
+            referenced: false,
+        };
+
+        if self.slots.len() < capacity {
+            self.index.insert(slot.key.clone(), self.slots.len());
+            self.slots.push(slot);
+            return;
+        }
+
+        while self.slots[self.hand].referenced {
+            self.slots[self.hand].referenced = false;
+            self.hand = (self.hand + 1) % self.slots.len();
+        }
+
+        self.index.remove(&self.slots[self.hand].key);
+        self.index.insert(slot.key.clone(), self.hand);
+        self.slots[self.hand] = slot;
+        self.hand = (self.hand + 1) % self.slots.len();
+    }
+
+    const fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn clear(&mut self) {
+        self.index.clear();
+        self.slots.clear();
+        self.hand = 0;
+    }
+}
+
+/// A bounded cache for [`to_ascii`] and [`to_unicode`].
+///
+/// Up to `capacity` results are kept for each direction, including errors.
+///
+/// # Examples
+///
+/// ```
+/// use edna::cache::Cache;
+///
+/// let mut cache = Cache::new(1000);
+/// assert_eq!(cache.to_ascii("münchen.de").as_deref(), Ok("xn--mnchen-3ya.de"));
+/// assert_eq!(cache.to_ascii("münchen.de").as_deref(), Ok("xn--mnchen-3ya.de"));
+/// assert_eq!(cache.stats().hits, 1);
+/// assert_eq!(cache.stats().misses, 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cache {
+    capacity: usize,
+    ascii: Clock<ToAsciiError>,
+    unicode: Clock<ToUnicodeError>,
+    stats: CacheStats,
+}
+
+impl Cache {
+    /// Creates an empty cache which holds up to `capacity` results per direction.
+    ///
+    /// A capacity of 0 disables caching, but still counts misses.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ascii: Clock::new(),
+            unicode: Clock::new(),
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Like [`to_ascii`], but answered from the cache if possible.
+    ///
+    /// # Errors
+    ///
+    /// See [`to_ascii`].
+    pub fn to_ascii(&mut self, s: &str) -> CacheResult {
+        Self::lookup(&mut self.ascii, &mut self.stats, self.capacity, s, |x| {
+            to_ascii(x).map(Into::into)
+        })
+    }
+
+    /// Like [`to_unicode`], but answered from the cache if possible.
+    ///
+    /// # Errors
+    ///
+    /// See [`to_unicode`].
+    pub fn to_unicode(&mut self, s: &str) -> CacheResult<ToUnicodeError> {
+        Self::lookup(&mut self.unicode, &mut self.stats, self.capacity, s, |x| {
+            to_unicode(x).map(Into::into)
+        })
+    }
+
+    fn lookup<E: Clone>(
+        clock: &mut Clock<E>,
+        stats: &mut CacheStats,
+        capacity: usize,
+        s: &str,
+        convert: impl FnOnce(&str) -> CacheResult<E>,
+    ) -> CacheResult<E> {
+        if let Some(ret) = clock.get(s) {
+            stats.hits += 1;
+            return ret;
+        }
+
+        stats.misses += 1;
+        let ret = convert(s);
+        clock.insert(s, ret.clone(), capacity);
+        ret
+    }
+
+    /// Returns the hit and miss counts since the cache was created or last cleared.
+    #[must_use]
+    pub const fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Returns the maximum number of results per direction.
+    #[must_use]
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of cached results, for both directions together.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.ascii.len() + self.unicode.len()
+    }
+
+    /// Returns `true` if no results are cached.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all cached results and resets the statistics.
+    pub fn clear(&mut self) {
+        self.ascii.clear();
+        self.unicode.clear();
+        self.stats = CacheStats::default();
+    }
+}
+
+/// A [`Cache`] which can be shared between threads.
+///
+/// All operations lock the whole cache, but only for the duration of a hash map lookup, or of an
+/// insertion after a miss. Conversions run without holding the lock.
+///
+/// # Examples
+///
+/// ```
+/// use edna::cache::SyncCache;
+///
+/// let cache = SyncCache::new(1000);
+/// std::thread::scope(|s| {
+///     for _ in 0..4 {
+///         s.spawn(|| assert_eq!(cache.to_unicode("xn--bcher-kva.de").as_deref(), Ok("bücher.de")));
+///     }
+/// });
+/// assert_eq!(cache.stats().hits + cache.stats().misses, 4);
+/// ```
+#[derive(Debug)]
+pub struct SyncCache(Mutex<Cache>);
+
+impl SyncCache {
+    /// Creates an empty cache which holds up to `capacity` results per direction.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self(Mutex::new(Cache::new(capacity)))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Cache> {
+        // The cache is always consistent between operations, so a panic elsewhere is harmless.
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Like [`to_ascii`], but answered from the cache if possible.
+    ///
+    /// # Errors
+    ///
+    /// See [`to_ascii`].
+    pub fn to_ascii(&self, s: &str) -> CacheResult {
+        self.lookup(s, |x| &mut x.ascii, |x| to_ascii(x).map(Into::into))
+    }
+
+    /// Like [`to_unicode`], but answered from the cache if possible.
+    ///
+    /// # Errors
+    ///
+    /// See [`to_unicode`].
+    pub fn to_unicode(&self, s: &str) -> CacheResult<ToUnicodeError> {
+        self.lookup(s, |x| &mut x.unicode, |x| to_unicode(x).map(Into::into))
+    }
+
+    fn lookup<E: Clone>(
+        &self,
+        s: &str,
+        clock: impl Fn(&mut Cache) -> &mut Clock<E>,
+        convert: impl FnOnce(&str) -> CacheResult<E>,
+    ) -> CacheResult<E> {
+        {
+            let mut cache = self.lock();
+            if let Some(ret) = clock(&mut cache).get(s) {
+                cache.stats.hits += 1;
+                return ret;
+            }
+            cache.stats.misses += 1;
+        }
+
+        let ret = convert(s);
+
+        let mut cache = self.lock();
+        let capacity = cache.capacity;
+        clock(&mut cache).insert(s, ret.clone(), capacity);
+        drop(cache);
+        ret
+    }
+
+    /// Returns the hit and miss counts since the cache was created or last cleared.
+    #[must_use]
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats()
+    }
+
+    /// Returns the number of cached results, for both directions together.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if no results are cached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Removes all cached results and resets the statistics.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::munchen("münchen.de", Ok("xn--mnchen-3ya.de"), Ok("münchen.de"))]
+    #[case::punycode("xn--mnchen-3ya.de", Ok("xn--mnchen-3ya.de"), Ok("münchen.de"))]
+    #[case::invalid(
+        "\u{10fff}",
+        Err(ToAsciiError::InvalidCharacter(Some('\u{10fff}'))),
+        Err(ToUnicodeError::InvalidCharacter(Some('\u{10fff}')))
+    )]
+    fn test_cache(
+        #[case] input: &str,
+        #[case] ascii: Result<&str, ToAsciiError>,
+        #[case] unicode: Result<&str, ToUnicodeError>,
+    ) {
+        let mut cache = Cache::new(10);
+        for _ in 0..3 {
+            assert_eq!(cache.to_ascii(input).as_deref(), ascii.as_deref());
+            assert_eq!(cache.to_unicode(input).as_deref(), unicode.as_deref());
+        }
+        assert_eq!(cache.stats(), CacheStats { hits: 4, misses: 2 });
+        assert_eq!(cache.len(), 2);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut cache = Cache::new(3);
+        for s in ["a", "b", "c"] {
+            cache.to_ascii(s).unwrap();
+        }
+        // "a" gets a second chance, so "b" is replaced
+        cache.to_ascii("a").unwrap();
+        cache.to_ascii("d").unwrap();
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 4 });
+
+        for s in ["a", "c", "d"] {
+            cache.to_ascii(s).unwrap();
+        }
+        assert_eq!(cache.stats(), CacheStats { hits: 4, misses: 4 });
+        cache.to_ascii("b").unwrap();
+        assert_eq!(cache.stats(), CacheStats { hits: 4, misses: 5 });
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut cache = Cache::new(0);
+        cache.to_ascii("a").unwrap();
+        cache.to_ascii("a").unwrap();
+        assert!(cache.is_empty());
+        assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 2 });
+        assert!(cache.stats().hit_rate().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_sync() {
+        let cache = SyncCache::new(100);
+        let inputs = (0..50)
+            .map(|i| i.to_string() + ".münchen.de")
+            .collect::<Vec<_>>();
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for x in &inputs {
+                        assert!(cache.to_ascii(x).unwrap().ends_with(".xn--mnchen-3ya.de"));
+                    }
+                });
+            }
+        });
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits + stats.misses, 200);
+        assert!(stats.misses >= 50);
+        assert!(cache.len() <= 100);
+    }
+}
@@ -0,0 +1,159 @@
+use core::{iter::FusedIterator, str::Chars};
+
+use crate::{
+    table::{lookup, Builtin, Table},
+    Mapping, ToAsciiError,
+};
+
+/// An iterator adapter applying the UTS #46 mapping step, created by [`map_chars`].
+///
+/// Yields the mapped characters of the underlying iterator one at a time, without collecting
+/// them into a string. A disallowed character is yielded as
+/// [`ToAsciiError::InvalidCharacter`], after which iteration can continue with the next one.
+///
+/// The output is not normalized.
+#[derive(Debug, Clone)]
+pub struct MapChars<'t, I, T: ?Sized = Builtin> {
+    iter: I,
+    table: &'t T,
+    /// The rest of the current mapping.
+    mapped: Chars<'t>,
+}
+
+/// Maps every character of `iter` like [`crate::map_validate`], but lazily.
+///
+/// # Examples
+///
+/// ```
+/// let mapped = edna::map_chars("ＭüNCHEN\u{AD}.de".chars()).collect::<Result<String, _>>();
+/// assert_eq!(mapped.as_deref(), Ok("münchen.de"));
+///
+/// let mut chars = edna::map_chars("a\u{10FFF}b".chars());
+/// assert_eq!(chars.next(), Some(Ok('a')));
+/// assert!(chars.next().unwrap().is_err());
+/// assert_eq!(chars.next(), Some(Ok('b')));
+/// ```
+pub fn map_chars<I>(iter: I) -> MapChars<'static, I::IntoIter>
+where
+    I: IntoIterator<Item = char>,
+{
+    MapChars::with_table(iter, &Builtin)
+}
+
+impl<'t, I, T> MapChars<'t, I, T>
+where
+    I: Iterator<Item = char>,
+    T: Table + ?Sized,
+{
+    /// Like [`map_chars`], but looks characters up in `table`.
+    pub fn with_table<J>(iter: J, table: &'t T) -> Self
+    where
+        J: IntoIterator<IntoIter = I>,
+    {
+        Self {
+            iter: iter.into_iter(),
+            table,
+            mapped: "".chars(),
+        }
+    }
+
+    /// Returns the underlying iterator.
+    ///
+    /// The rest of a mapping which was only partially yielded is lost.
+    pub fn into_inner(self) -> I {
+        self.iter
+    }
+}
+
+impl<I, T> Iterator for MapChars<'_, I, T>
+where
+    I: Iterator<Item = char>,
+    T: Table + ?Sized,
+{
+    type Item = Result<char, ToAsciiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(c) = self.mapped.next() {
+            return Some(Ok(c));
+        }
+
+        loop {
+            let c = self.iter.next()?;
+            if c.is_ascii() {
+                return Some(Ok(c.to_ascii_lowercase()));
+            }
+
+            match lookup(self.table, c).1 {
+                Mapping::Valid | Mapping::Deviation => return Some(Ok(c)),
+                Mapping::Ignored => (),
+                Mapping::Mapped(s) => {
+                    self.mapped = s.chars();
+                    if let Some(c) = self.mapped.next() {
+                        return Some(Ok(c));
+                    }
+                }
+                Mapping::Disallowed => return Some(Err(ToAsciiError::InvalidCharacter(Some(c)))),
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Characters may be ignored or mapped to several others.
+        (self.mapped.size_hint().0, None)
+    }
+}
+
+impl<I, T> FusedIterator for MapChars<'_, I, T>
+where
+    I: FusedIterator<Item = char>,
+    T: Table + ?Sized,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::{string::String, vec::Vec};
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::MappingTable;
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::ascii("WWW.Example.COM")]
+    #[case::mapped("ｍüｎｃｈｅｎ．de")]
+    #[case::ignored("exam\u{AD}ple")]
+    #[case::expanding("㍱.jp")]
+    #[case::deviation("faß.de")]
+    #[case::disallowed("a\u{10FFF}b")]
+    #[case::decomposed("mu\u{308}nchen")]
+    fn test_map_validate(#[case] input: &str) {
+        let mapped = map_chars(input.chars()).collect::<Result<String, _>>();
+        assert_eq!(mapped.as_deref(), crate::map_validate(input).as_deref());
+    }
+
+    #[test]
+    fn test_continue_after_error() {
+        let mapped = map_chars("Ａ\u{10FFF}⑴".chars()).collect::<Vec<_>>();
+        assert_eq!(
+            mapped,
+            [
+                Ok('a'),
+                Err(ToAsciiError::InvalidCharacter(Some('\u{10FFF}'))),
+                Ok('('),
+                Ok('1'),
+                Ok(')'),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_with_table() {
+        let table = MappingTable::parse("00C9 ; mapped ; 0065 0301\n").unwrap();
+        let mapped = MapChars::with_table("É".chars(), &table).collect::<Vec<_>>();
+        assert_eq!(mapped, [Ok('e'), Ok('\u{301}')]);
+    }
+}
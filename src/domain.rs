@@ -0,0 +1,329 @@
+//! Domain names which are known to have been processed by UTS #46.
+//!
+//! [`AsciiDomain`] and [`UnicodeDomain`] can only be created through [`to_ascii`] and
+//! [`to_unicode`], so they always hold the canonical form of a domain. Two domains which are
+//! equivalent under UTS #46 therefore compare equal, hash identically and sort next to each other.
+//!
+//! Both forms are computed on creation, so converting between the two types never fails.
+
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+extern crate alloc;
+use alloc::string::String;
+
+use crate::{to_ascii, to_unicode, Label, ToAsciiError};
+
+/// Returns the labels of `s`, ignoring the empty root label of a fully qualified domain.
+fn labels(s: &str) -> impl DoubleEndedIterator<Item = Label<'_>> {
+    let s = s.strip_suffix('.').unwrap_or(s);
+    crate::labels(s).filter(move |_| !s.is_empty())
+}
+
+/// Returns `s` without its first label and any empty labels following it.
+fn parent(s: &str) -> Option<&str> {
+    s.split_once('.')
+        .map(|(_, parent)| parent.trim_start_matches('.'))
+        .filter(|x| !x.is_empty())
+}
+
+macro_rules! impl_domain {
+    ($name:ident, $form:ident, $err:ty) => {
+        impl $name {
+            /// Returns the canonical form of the domain.
+            #[must_use]
+            pub fn as_str(&self) -> &str {
+                &self.$form
+            }
+
+            /// Consumes the domain, returning the underlying [`String`].
+            #[must_use]
+            pub fn into_string(self) -> String {
+                self.$form
+            }
+
+            /// Returns an iterator over the labels of the domain, from left to right.
+            ///
+            /// The empty root label of a fully qualified domain is not included.
+            pub fn labels(&self) -> impl DoubleEndedIterator<Item = Label<'_>> {
+                labels(&self.$form)
+            }
+
+            /// Returns the top-level domain, i.e. the rightmost label.
+            #[must_use]
+            pub fn tld(&self) -> Option<Label<'_>> {
+                self.labels().next_back()
+            }
+
+            /// Returns the parent domain, i.e. the domain without its leftmost label.
+            ///
+            /// Empty labels are skipped, so the parent of `a..b` is `b`. Returns [`None`] if the
+            /// domain has at most one non-empty label.
+            #[must_use]
+            pub fn parent(&self) -> Option<Self> {
+                // Both forms consist of the same labels.
+                Some(Self {
+                    ascii: parent(&self.ascii)?.into(),
+                    unicode: parent(&self.unicode)?.into(),
+                })
+            }
+        }
+
+        // The other form is derived from this one, so it can be ignored.
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.$form == other.$form
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.$form.hash(state);
+            }
+        }
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.$form.cmp(&other.$form)
+            }
+        }
+
+        impl TryFrom<&str> for $name {
+            type Error = $err;
+
+            fn try_from(s: &str) -> Result<Self, Self::Error> {
+                Self::new(s)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = $err;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::new(s)
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.$form
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(d: $name) -> Self {
+                d.$form
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.$form)
+            }
+        }
+    };
+}
+
+/// A domain in its ASCII form, as returned by [`to_ascii`].
+///
+/// # Examples
+///
+/// ```
+/// use edna::AsciiDomain;
+///
+/// let a: AsciiDomain = "www.München.de".parse().unwrap();
+/// let b: AsciiDomain = "WWW.xn--mnchen-3ya.DE".parse().unwrap();
+/// assert_eq!(a, b);
+/// assert_eq!(a.as_str(), "www.xn--mnchen-3ya.de");
+/// assert_eq!(a.parent().unwrap().as_str(), "xn--mnchen-3ya.de");
+/// assert_eq!(a.to_unicode().as_str(), "www.münchen.de");
+/// ```
+#[derive(Debug, Clone)]
+pub struct AsciiDomain {
+    ascii: String,
+    unicode: String,
+}
+
+impl AsciiDomain {
+    /// Processes `s` with [`to_ascii`].
+    ///
+    /// # Errors
+    ///
+    /// - See [`to_ascii`].
+    /// - A Punycode label in `s` can not be converted by [`to_unicode`].
+    pub fn new(s: &str) -> Result<Self, ToAsciiError> {
+        let ascii = to_ascii(s)?;
+        let unicode = to_unicode(&ascii)?.into_owned();
+        Ok(Self {
+            ascii: ascii.into_owned(),
+            unicode,
+        })
+    }
+
+    /// Converts the domain to its Unicode form.
+    #[must_use]
+    pub fn to_unicode(&self) -> UnicodeDomain {
+        self.clone().into()
+    }
+}
+
+impl From<UnicodeDomain> for AsciiDomain {
+    fn from(d: UnicodeDomain) -> Self {
+        Self {
+            ascii: d.ascii,
+            unicode: d.unicode,
+        }
+    }
+}
+
+impl_domain!(AsciiDomain, ascii, ToAsciiError);
+
+/// A domain in its Unicode form, as returned by [`to_unicode`].
+///
+/// # Examples
+///
+/// ```
+/// use edna::UnicodeDomain;
+///
+/// let a: UnicodeDomain = "www.München.de".parse().unwrap();
+/// let b: UnicodeDomain = "WWW.xn--mnchen-3ya.DE".parse().unwrap();
+/// assert_eq!(a, b);
+/// assert_eq!(a.as_str(), "www.münchen.de");
+/// assert_eq!(a.tld().unwrap().as_str(), "de");
+/// assert_eq!(a.to_ascii().as_str(), "www.xn--mnchen-3ya.de");
+/// ```
+#[derive(Debug, Clone)]
+pub struct UnicodeDomain {
+    ascii: String,
+    unicode: String,
+}
+
+impl UnicodeDomain {
+    /// Processes `s` with [`to_unicode`].
+    ///
+    /// # Errors
+    ///
+    /// - See [`to_unicode`].
+    /// - The result can not be converted by [`to_ascii`].
+    pub fn new(s: &str) -> Result<Self, ToAsciiError> {
+        let unicode = to_unicode(s)?;
+        let ascii = to_ascii(&unicode)?.into_owned();
+        Ok(Self {
+            ascii,
+            unicode: unicode.into_owned(),
+        })
+    }
+
+    /// Converts the domain to its ASCII form.
+    #[must_use]
+    pub fn to_ascii(&self) -> AsciiDomain {
+        self.clone().into()
+    }
+}
+
+impl From<AsciiDomain> for UnicodeDomain {
+    fn from(d: AsciiDomain) -> Self {
+        Self {
+            ascii: d.ascii,
+            unicode: d.unicode,
+        }
+    }
+}
+
+impl_domain!(UnicodeDomain, unicode, ToAsciiError);
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::vec::Vec;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::simple("www.example.com", &["www", "example", "com"])]
+    #[case::fqdn("www.example.com.", &["www", "example", "com"])]
+    #[case::single("com", &["com"])]
+    #[case::root(".", &[])]
+    #[case::empty("", &[])]
+    #[case::empty_label("a..b", &["a", "", "b"])]
+    fn test_labels(#[case] input: &str, #[case] expected: &[&str]) {
+        let got = labels(input).map(|x| x.as_str()).collect::<Vec<_>>();
+        assert_eq!(got, expected);
+    }
+
+    #[rstest]
+    #[case::simple("www.example.com", Some("example.com"))]
+    #[case::fqdn("example.com.", Some("com."))]
+    #[case::tld("com", None)]
+    #[case::tld_fqdn("com.", None)]
+    #[case::empty("", None)]
+    #[case::empty_label("a..b", Some("b"))]
+    #[case::empty_label_fqdn("a..b.", Some("b."))]
+    #[case::empty_labels("a...", None)]
+    fn test_parent(#[case] input: &str, #[case] expected: Option<&str>) {
+        assert_eq!(parent(input), expected);
+    }
+
+    #[rstest]
+    #[case::case("www.EXAMPLE.com", "www.example.com")]
+    #[case::unicode("München.de", "xn--mnchen-3ya.de")]
+    #[case::fullwidth("ｍüｎｃｈｅｎ.de", "xn--mnchen-3ya.de")]
+    #[case::ideographic_dot("münchen。de", "xn--mnchen-3ya.de")]
+    fn test_equivalence(#[case] a: &str, #[case] b: &str) {
+        let a_ascii = AsciiDomain::new(a).unwrap();
+        let b_ascii = AsciiDomain::new(b).unwrap();
+        assert_eq!(a_ascii, b_ascii);
+        assert_eq!(a_ascii.to_unicode(), UnicodeDomain::new(b).unwrap());
+        assert_eq!(UnicodeDomain::new(a).unwrap().to_ascii(), b_ascii);
+    }
+
+    #[test]
+    fn test_tld() {
+        let a = AsciiDomain::new("a.com").unwrap();
+        let b = AsciiDomain::new("bb.com").unwrap();
+        assert_eq!(a.tld(), b.tld());
+    }
+
+    #[test]
+    fn test_parent_forms() {
+        let ascii = AsciiDomain::new("www.München.de.").unwrap();
+        let parent = ascii.parent().unwrap();
+        assert_eq!(parent.as_str(), "xn--mnchen-3ya.de.");
+        assert_eq!(parent.to_unicode().as_str(), "münchen.de.");
+        assert_eq!(
+            parent.to_unicode().parent().unwrap().to_ascii().as_str(),
+            "de."
+        );
+    }
+
+    #[test]
+    fn test_parent_empty_label() {
+        let ascii = AsciiDomain::new("www..München.de").unwrap();
+        let parent = ascii.parent().unwrap();
+        assert_eq!(parent.as_str(), "xn--mnchen-3ya.de");
+        assert_eq!(parent.to_unicode().as_str(), "münchen.de");
+    }
+
+    #[test]
+    fn test_invalid_punycode() {
+        assert_eq!(
+            AsciiDomain::new("xn--a.com"),
+            Err(ToAsciiError::InvalidPunycode("xn--a".into()))
+        );
+    }
+}
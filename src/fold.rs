@@ -0,0 +1,118 @@
+extern crate alloc;
+use alloc::{borrow::Cow, string::String};
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::{
+    table::{lookup, Table},
+    Mapping, Processor,
+};
+
+/// What [`fold`] does with disallowed characters.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum OnDisallowed {
+    /// Keeps the character unchanged.
+    #[default]
+    Keep,
+    /// Removes the character.
+    Remove,
+    /// Replaces the character with another one, such as U+FFFD REPLACEMENT CHARACTER.
+    Replace(char),
+}
+
+/// Applies the case and compatibility folding of UTS #46 to arbitrary text, then normalizes it
+/// to NFC.
+///
+/// Unlike [`crate::map_validate`], this never fails: disallowed characters are handled as
+/// `on_disallowed` says. This makes it suitable for search keys and deduplication. Returns
+/// [`Cow::Borrowed`] if nothing changes.
+///
+/// # Examples
+///
+/// ```
+/// use edna::OnDisallowed;
+///
+/// assert_eq!(edna::fold("Ça Ｖａ\u{AD}!", OnDisallowed::Keep), "ça va!");
+/// assert_eq!(edna::fold("a\u{10FFF}b", OnDisallowed::Replace('\u{FFFD}')), "a\u{FFFD}b");
+/// ```
+#[must_use]
+pub fn fold(s: &str, on_disallowed: OnDisallowed) -> Cow<'_, str> {
+    Processor::new().fold(s, on_disallowed)
+}
+
+/// Like [`fold`], but looks characters up in `table`.
+pub fn fold_with<'a, T: Table + ?Sized>(
+    table: &T,
+    s: &'a str,
+    on_disallowed: OnDisallowed,
+) -> Cow<'a, str> {
+    let unchanged = s.chars().all(|c| {
+        if c.is_ascii() {
+            return !c.is_ascii_uppercase();
+        }
+        match lookup(table, c).1 {
+            Mapping::Valid | Mapping::Deviation => true,
+            Mapping::Disallowed => on_disallowed == OnDisallowed::Keep,
+            Mapping::Ignored | Mapping::Mapped(_) => false,
+        }
+    });
+    if unchanged && is_nfc_quick(s.chars()) == IsNormalized::Yes {
+        return Cow::Borrowed(s);
+    }
+
+    let mut mapped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c.is_ascii() {
+            mapped.push(c.to_ascii_lowercase());
+            continue;
+        }
+
+        match lookup(table, c).1 {
+            Mapping::Valid | Mapping::Deviation => mapped.push(c),
+            Mapping::Ignored => (),
+            Mapping::Mapped(r) => mapped.push_str(r),
+            Mapping::Disallowed => match on_disallowed {
+                OnDisallowed::Keep => mapped.push(c),
+                OnDisallowed::Remove => (),
+                OnDisallowed::Replace(r) => mapped.push(r),
+            },
+        }
+    }
+
+    if is_nfc_quick(mapped.chars()) == IsNormalized::Yes {
+        Cow::Owned(mapped)
+    } else {
+        Cow::Owned(mapped.nfc().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::unchanged("hello world", OnDisallowed::Keep, "hello world")]
+    #[case::case("Ça Va", OnDisallowed::Keep, "ça va")]
+    #[case::fullwidth("ＡＢＣ１２３", OnDisallowed::Keep, "abc123")]
+    #[case::soft_hyphen("dedu\u{AD}plication", OnDisallowed::Keep, "deduplication")]
+    #[case::deviation("Straße", OnDisallowed::Keep, "straße")]
+    #[case::compose("e\u{301}te\u{301}", OnDisallowed::Keep, "été")]
+    #[case::keep("a\u{10FFF}b", OnDisallowed::Keep, "a\u{10FFF}b")]
+    #[case::remove("a\u{10FFF}b", OnDisallowed::Remove, "ab")]
+    #[case::replace("a\u{10FFF}b", OnDisallowed::Replace('\u{FFFD}'), "a\u{FFFD}b")]
+    fn test_fold(#[case] input: &str, #[case] on_disallowed: OnDisallowed, #[case] expected: &str) {
+        let folded = fold(input, on_disallowed);
+        assert_eq!(folded, expected);
+        assert_eq!(matches!(folded, Cow::Borrowed(_)), input == expected);
+    }
+
+    #[test]
+    fn test_max_age() {
+        let processor = Processor::new().with_max_age((9, 0));
+        assert_eq!(processor.fold("🦀🦒", OnDisallowed::Remove), "🦀");
+    }
+}
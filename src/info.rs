@@ -0,0 +1,126 @@
+use crate::{
+    data,
+    table::{lookup, Builtin},
+    Mapping,
+};
+
+/// Information about a single code point, see [`char_info`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CharInfo {
+    /// The mapping of the code point. Uppercase ASCII letters are mapped to lowercase.
+    pub mapping: Mapping<'static>,
+    /// The version of Unicode which introduced the code point as `(major, minor)`, or `None` if
+    /// it is unassigned.
+    pub age: Option<(u8, u8)>,
+    /// The name of the code point.
+    ///
+    /// This is always `None` without the `names` feature. The mapping table only names single
+    /// code points and the ends of ranges, so most CJK ideographs and Hangul syllables have no
+    /// name either.
+    pub name: Option<&'static str>,
+}
+
+/// Returns the mapping, age and name of `c`, as given by the built-in mapping table.
+///
+/// # Examples
+///
+/// ```
+/// use edna::{char_info, Mapping};
+///
+/// let info = char_info('Ａ');
+/// assert_eq!(info.mapping, Mapping::Mapped("a"));
+/// assert_eq!(info.age, Some((1, 1)));
+/// # #[cfg(feature = "names")]
+/// assert_eq!(info.name, Some("FULLWIDTH LATIN CAPITAL LETTER A"));
+///
+/// assert_eq!(char_info('\u{10FFF}').age, None);
+/// ```
+#[must_use]
+pub fn char_info(c: char) -> CharInfo {
+    CharInfo {
+        mapping: mapping(c),
+        age: age(c),
+        name: name(c),
+    }
+}
+
+/// Returns the mapping of `c`, including ASCII.
+fn mapping(c: char) -> Mapping<'static> {
+    if c.is_ascii() {
+        ascii_mapping(c)
+    } else {
+        lookup(&Builtin, c).1
+    }
+}
+
+/// Returns the mapping of the ASCII character `c`, which is not part of the tables.
+pub fn ascii_mapping(c: char) -> Mapping<'static> {
+    const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+
+    if c.is_ascii_uppercase() {
+        let i = usize::from(c as u8 - b'A');
+        Mapping::Mapped(&LOWERCASE[i..=i])
+    } else {
+        Mapping::Valid
+    }
+}
+
+/// Returns the version of Unicode which introduced `c`.
+#[cfg(not(feature = "small-tables"))]
+pub fn age(c: char) -> Option<(u8, u8)> {
+    let i = data::AGES.partition_point(|&(start, _)| start <= c);
+    data::AGES.get(i.checked_sub(1)?)?.1
+}
+
+/// Returns the version of Unicode which introduced `c`.
+#[cfg(feature = "small-tables")]
+pub fn age(c: char) -> Option<(u8, u8)> {
+    // Every entry holds a start in the upper bits and `major << 3 | minor` in the low byte.
+    let i = data::AGES.partition_point(|&x| x >> 8 <= c as u32);
+    let [code, ..] = data::AGES.get(i.checked_sub(1)?)?.to_le_bytes();
+    Some((code >> 3, code & 0b111)).filter(|_| code != 0)
+}
+
+#[cfg(feature = "names")]
+fn name(c: char) -> Option<&'static str> {
+    let i = data::NAMED.binary_search(&c).ok()?;
+    let start = i.checked_sub(1).map_or(0, |x| data::NAME_ENDS[x] as usize);
+    data::NAMES.get(start..data::NAME_ENDS[i] as usize)
+}
+
+#[cfg(not(feature = "names"))]
+const fn name(_: char) -> Option<&'static str> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::ascii('A', Mapping::Mapped("a"), Some((1, 1)), Some("LATIN CAPITAL LETTER A"))]
+    #[case::digit('5', Mapping::Valid, Some((1, 1)), None)]
+    #[case::control('\0', Mapping::Valid, Some((1, 1)), None)]
+    #[case::deviation('ß', Mapping::Valid, Some((1, 1)), Some("LATIN SMALL LETTER SHARP S"))]
+    #[case::ignored('\u{AD}', Mapping::Ignored, Some((1, 1)), Some("SOFT HYPHEN"))]
+    #[case::emoji('🦀', Mapping::Valid, Some((8, 0)), Some("CRAB"))]
+    #[case::new('\u{1C89}', Mapping::Mapped("\u{1C8A}"), Some((16, 0)), Some("CYRILLIC CAPITAL LETTER TJE"))]
+    #[case::unassigned('\u{10FFF}', Mapping::Disallowed, None, None)]
+    #[case::last(char::MAX, Mapping::Disallowed, Some((2, 0)), None)]
+    fn test_char_info(
+        #[case] c: char,
+        #[case] mapping: Mapping<'static>,
+        #[case] age: Option<(u8, u8)>,
+        #[case] name: Option<&str>,
+    ) {
+        let info = char_info(c);
+        assert_eq!((info.mapping, info.age), (mapping, age));
+        if cfg!(feature = "names") {
+            assert_eq!(info.name, name);
+        } else {
+            assert_eq!(info.name, None);
+        }
+    }
+}
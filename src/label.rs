@@ -0,0 +1,606 @@
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
+    ops::Range,
+    str::Split,
+};
+
+extern crate alloc;
+use alloc::borrow::Cow;
+
+use thiserror::Error;
+use unicode_normalization::is_nfc;
+
+use crate::{
+    has_punycode_prefix,
+    punycode::{self, PunyDecodeError},
+    table::{lookup, Builtin},
+    validate, Mapping, Processor, ToAsciiError, ToUnicodeError, PREFIX,
+};
+
+/// The maximum length of a label in octets, see RFC 1034.
+const MAX_LEN: usize = 63;
+
+/// The kind of a [`Label`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LabelKind {
+    /// The label is empty, e.g. the root label after a trailing dot.
+    Empty,
+    /// The label consists of ASCII letters, digits and hyphens only.
+    Ldh,
+    /// The label is ASCII, but contains characters other than letters, digits and hyphens.
+    NonLdh,
+    /// The label starts with the Punycode prefix and decodes to a valid U-label.
+    ALabel,
+    /// The label starts with the Punycode prefix, but does not decode to a valid U-label.
+    FakeALabel,
+    /// The label contains non-ASCII characters.
+    ULabel,
+}
+
+/// A single label of a domain, i.e. the text between two dots.
+///
+/// Labels compare, hash and sort by their text only, regardless of where they are found.
+#[derive(Debug, Copy, Clone)]
+pub struct Label<'a> {
+    text: &'a str,
+    start: usize,
+}
+
+impl<'a> Label<'a> {
+    pub(crate) const fn new(text: &'a str, start: usize) -> Self {
+        Self { text, start }
+    }
+
+    /// Returns the text of the label, without any dots.
+    #[must_use]
+    pub const fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    /// Returns `true` if the label is empty.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Returns the byte range of the label in the domain it was taken from.
+    #[must_use]
+    pub const fn span(&self) -> Range<usize> {
+        self.start..self.start + self.text.len()
+    }
+
+    /// Classifies the label.
+    ///
+    /// The label is expected to be mapped already, see [`crate::map_validate`]. Telling an
+    /// A-label from a fake A-label requires decoding it.
+    #[must_use]
+    pub fn kind(&self) -> LabelKind {
+        let s = self.text;
+
+        if s.is_empty() {
+            return LabelKind::Empty;
+        }
+
+        if has_punycode_prefix(s.as_bytes()) {
+            let is_valid = s.is_ascii()
+                && punycode::decode(&s[PREFIX.len()..])
+                    .is_ok_and(|x| !x.is_ascii() && is_nfc(&x) && validate(&x).is_ok());
+            return if is_valid {
+                LabelKind::ALabel
+            } else {
+                LabelKind::FakeALabel
+            };
+        }
+
+        if !s.is_ascii() {
+            LabelKind::ULabel
+        } else if s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+            LabelKind::Ldh
+        } else {
+            LabelKind::NonLdh
+        }
+    }
+}
+
+impl PartialEq for Label<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for Label<'_> {}
+
+impl Hash for Label<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.text.hash(state);
+    }
+}
+
+impl PartialOrd for Label<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Label<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.text.cmp(other.text)
+    }
+}
+
+impl AsRef<str> for Label<'_> {
+    fn as_ref(&self) -> &str {
+        self.text
+    }
+}
+
+impl fmt::Display for Label<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text)
+    }
+}
+
+/// An iterator over the [`Label`]s of a domain, see [`labels`].
+#[derive(Debug, Clone)]
+pub struct Labels<'a> {
+    domain: &'a str,
+    split: Split<'a, char>,
+}
+
+impl<'a> Labels<'a> {
+    fn label(&self, text: &'a str) -> Label<'a> {
+        let start = text.as_ptr() as usize - self.domain.as_ptr() as usize;
+        Label::new(text, start)
+    }
+}
+
+impl<'a> Iterator for Labels<'a> {
+    type Item = Label<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.split.next().map(|x| self.label(x))
+    }
+}
+
+impl DoubleEndedIterator for Labels<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.split.next_back().map(|x| self.label(x))
+    }
+}
+
+impl FusedIterator for Labels<'_> {}
+
+/// Returns an iterator over the labels of a mapped domain.
+///
+/// Every dot separates two labels, so a trailing dot results in an empty root label, and the
+/// empty string consists of a single empty label.
+///
+/// # Examples
+///
+/// ```
+/// use edna::{labels, LabelKind};
+///
+/// let kinds = labels("www.xn--mnchen-3ya.bücher.xn--abc.")
+///     .map(|x| x.kind())
+///     .collect::<Vec<_>>();
+/// assert_eq!(
+///     kinds,
+///     [
+///         LabelKind::Ldh,
+///         LabelKind::ALabel,
+///         LabelKind::ULabel,
+///         LabelKind::FakeALabel,
+///         LabelKind::Empty,
+///     ]
+/// );
+/// ```
+#[must_use]
+pub fn labels(domain: &str) -> Labels<'_> {
+    Labels {
+        domain,
+        split: domain.split('.'),
+    }
+}
+
+/// Converts a single label to its ASCII form, like [`crate::to_ascii`] does for every label of a
+/// domain.
+///
+/// The label is not split at dots. This suits protocols which carry labels on their own, such as
+/// DNS-SD instance names.
+///
+/// # Errors
+///
+/// - `label` contains a dot, or a character which is mapped to one.
+/// - See [`crate::to_ascii`].
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(edna::label_to_ascii("Bücher").as_deref(), Ok("xn--bcher-kva"));
+/// assert!(edna::label_to_ascii("bücher.de").is_err());
+/// assert!(edna::label_to_ascii("bücher．de").is_err());
+/// ```
+pub fn label_to_ascii(label: &str) -> Result<Cow<'_, str>, ToAsciiError> {
+    Processor::new().label_to_ascii(label)
+}
+
+/// Converts a single label to its Unicode form, like [`crate::to_unicode`] does for every label
+/// of a domain.
+///
+/// # Errors
+///
+/// - `label` contains a dot, or a character which is mapped to one.
+/// - See [`crate::to_unicode`].
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(edna::label_to_unicode("xn--bcher-kva").as_deref(), Ok("bücher"));
+/// assert!(edna::label_to_unicode("xn--a").is_err());
+/// ```
+pub fn label_to_unicode(label: &str) -> Result<Cow<'_, str>, ToUnicodeError> {
+    Processor::new().label_to_unicode(label)
+}
+
+/// Checks that a single label is already mapped and contains no dots.
+///
+/// # Errors
+///
+/// - `label` contains a dot.
+/// - See [`crate::validate`].
+pub fn validate_label(label: &str) -> Result<(), ToAsciiError> {
+    Processor::new().validate_label(label)
+}
+
+/// Why a label is not of the kind checked by [`is_ldh_label`], [`is_a_label`] or [`is_u_label`].
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum LabelError {
+    #[error("empty label")]
+    Empty,
+    /// The label, or the A-label of a U-label, is longer than 63 octets.
+    #[error("label is {0} octets long")]
+    TooLong(usize),
+    #[error("invalid character: {0}")]
+    InvalidCharacter(char),
+    #[error("label starts or ends with a hyphen")]
+    Hyphen,
+    /// The label has hyphens in its third and fourth positions, like `xn--`.
+    #[error("label is reserved")]
+    Reserved,
+    /// A U-label consists of ASCII only, or an A-label decodes to ASCII only.
+    #[error("label is ASCII")]
+    Ascii,
+    #[error("label is not NFC")]
+    NotNfc,
+    #[error("label does not start with the Punycode prefix")]
+    MissingPrefix,
+    #[error("invalid punycode: {0}")]
+    InvalidPunycode(#[from] PunyDecodeError),
+    /// An A-label is not the lowercase encoding of the U-label it decodes to.
+    #[error("label is not in canonical form")]
+    NotCanonical,
+}
+
+/// Checks the hyphen rules shared by all kinds of labels.
+fn check_hyphens(label: &str) -> Result<(), LabelError> {
+    if label.starts_with('-') || label.ends_with('-') {
+        return Err(LabelError::Hyphen);
+    }
+    if label.chars().skip(2).take(2).eq(['-', '-']) {
+        return Err(LabelError::Reserved);
+    }
+
+    Ok(())
+}
+
+/// Checks that `label` is a non-reserved LDH label as defined by RFC 5890.
+///
+/// That is, `label` consists of 1 to 63 ASCII letters, digits and hyphens, does not start or end
+/// with a hyphen, and does not have hyphens in its third and fourth positions. Labels with such
+/// hyphens, which include all A-labels, fail with [`LabelError::Reserved`].
+///
+/// # Errors
+///
+/// Returns the first rule that `label` breaks.
+///
+/// # Examples
+///
+/// ```
+/// use edna::LabelError;
+///
+/// assert_eq!(edna::is_ldh_label("Example-1"), Ok(()));
+/// assert_eq!(edna::is_ldh_label("xn--bcher-kva"), Err(LabelError::Reserved));
+/// assert_eq!(edna::is_ldh_label("a_b"), Err(LabelError::InvalidCharacter('_')));
+/// ```
+pub fn is_ldh_label(label: &str) -> Result<(), LabelError> {
+    if label.is_empty() {
+        return Err(LabelError::Empty);
+    }
+    if label.len() > MAX_LEN {
+        return Err(LabelError::TooLong(label.len()));
+    }
+    if let Some(c) = label
+        .chars()
+        .find(|&c| !c.is_ascii_alphanumeric() && c != '-')
+    {
+        return Err(LabelError::InvalidCharacter(c));
+    }
+
+    check_hyphens(label)
+}
+
+/// Checks that `label` is an A-label as defined by RFC 5890.
+///
+/// That is, `label` starts with the Punycode prefix, has the syntax of an LDH label apart from the
+/// reserved hyphens, and decodes to a U-label, see [`is_u_label`]. It must also be exactly what
+/// encoding that U-label gives, so uppercase letters are rejected.
+///
+/// # Errors
+///
+/// Returns the first rule that `label` or the U-label it decodes to breaks.
+///
+/// # Examples
+///
+/// ```
+/// use edna::LabelError;
+///
+/// assert_eq!(edna::is_a_label("xn--bcher-kva"), Ok(()));
+/// assert_eq!(edna::is_a_label("XN--BCHER-KVA"), Err(LabelError::NotCanonical));
+/// assert_eq!(edna::is_a_label("bcher-kva"), Err(LabelError::MissingPrefix));
+/// assert_eq!(edna::is_a_label("xn--abc-"), Err(LabelError::Hyphen));
+/// ```
+pub fn is_a_label(label: &str) -> Result<(), LabelError> {
+    if !has_punycode_prefix(label.as_bytes()) {
+        return Err(LabelError::MissingPrefix);
+    }
+    match is_ldh_label(label) {
+        Ok(()) | Err(LabelError::Reserved) => (),
+        Err(e) => return Err(e),
+    }
+
+    if label.bytes().any(|b| b.is_ascii_uppercase()) {
+        return Err(LabelError::NotCanonical);
+    }
+
+    // The label has been checked to be ASCII, so slicing is fine.
+    let decoded = punycode::decode(&label[PREFIX.len()..])?;
+    is_u_label(&decoded)?;
+    if punycode::encode(&decoded).is_ok_and(|x| x != label[PREFIX.len()..]) {
+        return Err(LabelError::NotCanonical);
+    }
+
+    Ok(())
+}
+
+/// Checks that `label` is a U-label as defined by RFC 5890.
+///
+/// That is, `label` contains at least one non-ASCII character, and otherwise only lowercase ASCII
+/// letters, digits and hyphens. Non-ASCII characters must be valid according to the UTS #46
+/// mapping table, and the label must be NFC. The hyphen rules of LDH labels apply as well, and
+/// the corresponding A-label must be no longer than 63 octets.
+///
+/// # Errors
+///
+/// Returns the first rule that `label` breaks.
+///
+/// # Examples
+///
+/// ```
+/// use edna::LabelError;
+///
+/// assert_eq!(edna::is_u_label("bücher"), Ok(()));
+/// assert_eq!(edna::is_u_label("Bücher"), Err(LabelError::InvalidCharacter('B')));
+/// assert_eq!(edna::is_u_label("bu\u{308}cher"), Err(LabelError::NotNfc));
+/// assert_eq!(edna::is_u_label("books"), Err(LabelError::Ascii));
+/// ```
+pub fn is_u_label(label: &str) -> Result<(), LabelError> {
+    if label.is_empty() {
+        return Err(LabelError::Empty);
+    }
+    if label.is_ascii() {
+        return Err(LabelError::Ascii);
+    }
+
+    let invalid = label.chars().find(|&c| {
+        if c.is_ascii() {
+            !(c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        } else {
+            lookup(&Builtin, c).1 != Mapping::Valid
+        }
+    });
+    if let Some(c) = invalid {
+        return Err(LabelError::InvalidCharacter(c));
+    }
+    if !is_nfc(label) {
+        return Err(LabelError::NotNfc);
+    }
+    check_hyphens(label)?;
+
+    // Encoding only overflows far beyond the maximum length.
+    let len = punycode::encode(label).map_or(usize::MAX, |x| PREFIX.len() + x.len());
+    if len > MAX_LEN {
+        return Err(LabelError::TooLong(len));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use alloc::{format, vec::Vec};
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::ldh("www", LabelKind::Ldh)]
+    #[case::ldh_hyphen("a-1", LabelKind::Ldh)]
+    #[case::non_ldh("hola!", LabelKind::NonLdh)]
+    #[case::a_label("xn--mnchen-3ya", LabelKind::ALabel)]
+    #[case::a_label_uppercase("XN--MNCHEN-3YA", LabelKind::FakeALabel)]
+    #[case::fake_ascii("xn--abc-", LabelKind::FakeALabel)]
+    #[case::fake_invalid("xn--a", LabelKind::FakeALabel)]
+    #[case::fake_non_ascii("xn--ü", LabelKind::FakeALabel)]
+    #[case::u_label("münchen", LabelKind::ULabel)]
+    #[case::empty("", LabelKind::Empty)]
+    fn test_kind(#[case] input: &str, #[case] expected: LabelKind) {
+        assert_eq!(labels(input).next().unwrap().kind(), expected);
+    }
+
+    #[rstest]
+    #[case::simple("www.example.com", &[(0, 3), (4, 11), (12, 15)])]
+    #[case::unicode("ü.b", &[(0, 2), (3, 4)])]
+    #[case::fqdn("a.", &[(0, 1), (2, 2)])]
+    #[case::empty_label("a..b", &[(0, 1), (2, 2), (3, 4)])]
+    #[case::empty("", &[(0, 0)])]
+    fn test_span(#[case] input: &str, #[case] expected: &[(usize, usize)]) {
+        let got = labels(input)
+            .map(|x| (x.span().start, x.span().end))
+            .collect::<Vec<_>>();
+        assert_eq!(got, expected);
+        for label in labels(input) {
+            assert_eq!(&input[label.span()], label.as_str());
+        }
+        let got = labels(input)
+            .rev()
+            .map(|x| (x.span().start, x.span().end))
+            .collect::<Vec<_>>();
+        assert!(got.iter().eq(expected.iter().rev()));
+    }
+
+    #[test]
+    fn test_eq() {
+        // labels from different domains, or from different positions
+        let a = labels("a.com").next_back().unwrap();
+        let b = labels("bb.com").next_back().unwrap();
+        let c = labels("com.net").next().unwrap();
+        assert_ne!(a.span(), b.span());
+        assert_eq!(a, b);
+        assert_eq!(a, c);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_eq!(std::collections::HashSet::from([a, b, c]).len(), 1);
+
+        assert_ne!(a, labels("a.net").next_back().unwrap());
+        assert!(a < labels("a.net").next_back().unwrap());
+    }
+
+    #[rstest]
+    #[case::ldh("www")]
+    #[case::uppercase("WWW")]
+    #[case::empty("")]
+    #[case::u_label("Bücher")]
+    #[case::a_label("xn--bcher-kva")]
+    #[case::fake_a_label("xn--a")]
+    #[case::decomposed("bu\u{308}cher")]
+    #[case::disallowed("a\u{10FFF}")]
+    fn test_same_as_domain(#[case] input: &str) {
+        assert_eq!(label_to_ascii(input), crate::to_ascii(input));
+        assert_eq!(label_to_unicode(input), crate::to_unicode(input));
+        assert_eq!(validate_label(input), crate::validate(input));
+    }
+
+    #[rstest]
+    #[case::dot("a.b")]
+    #[case::trailing_dot("a.")]
+    #[case::mapped_dot("a．b")]
+    #[case::ideographic_dot("a。b")]
+    fn test_dot(#[case] input: &str) {
+        let error = ToAsciiError::InvalidCharacter(Some('.'));
+        assert_eq!(label_to_ascii(input), Err(error.clone()));
+        assert_eq!(
+            label_to_unicode(input),
+            Err(ToUnicodeError::InvalidCharacter(Some('.')))
+        );
+        if input.is_ascii() {
+            assert_eq!(validate_label(input), Err(error));
+        } else {
+            assert!(validate_label(input).is_err());
+        }
+    }
+
+    #[test]
+    fn test_max_age() {
+        let processor = Processor::new().with_max_age((9, 0));
+        let error = ToAsciiError::TooNew('🦒', 10, 0);
+        assert_eq!(processor.label_to_ascii("🦒"), Err(error.clone()));
+        assert_eq!(
+            processor.label_to_unicode("🦒"),
+            Err(ToUnicodeError::TooNew('🦒', 10, 0))
+        );
+        assert_eq!(processor.validate_label("🦒"), Err(error));
+    }
+
+    #[rstest]
+    #[case::ldh("www", Ok(()))]
+    #[case::uppercase("WWW", Ok(()))]
+    #[case::digits("123", Ok(()))]
+    #[case::max_len(&"a".repeat(63), Ok(()))]
+    #[case::too_long(&"a".repeat(64), Err(LabelError::TooLong(64)))]
+    #[case::empty("", Err(LabelError::Empty))]
+    #[case::underscore("_dmarc", Err(LabelError::InvalidCharacter('_')))]
+    #[case::non_ascii("bücher", Err(LabelError::InvalidCharacter('ü')))]
+    #[case::leading_hyphen("-a", Err(LabelError::Hyphen))]
+    #[case::trailing_hyphen("a-", Err(LabelError::Hyphen))]
+    #[case::reserved("ab--c", Err(LabelError::Reserved))]
+    #[case::a_label("xn--bcher-kva", Err(LabelError::Reserved))]
+    fn test_is_ldh_label(#[case] input: &str, #[case] expected: Result<(), LabelError>) {
+        assert_eq!(is_ldh_label(input), expected);
+    }
+
+    #[rstest]
+    #[case::a_label("xn--bcher-kva", Ok(()))]
+    #[case::uppercase_prefix("XN--bcher-kva", Err(LabelError::NotCanonical))]
+    #[case::uppercase("xn--Bcher-kva", Err(LabelError::NotCanonical))]
+    #[case::missing_prefix("bcher-kva", Err(LabelError::MissingPrefix))]
+    #[case::prefix_only("xn--", Err(LabelError::Hyphen))]
+    #[case::non_ascii("xn--ü", Err(LabelError::InvalidCharacter('ü')))]
+    #[case::invalid_punycode(
+        "xn--zz",
+        Err(LabelError::InvalidPunycode(PunyDecodeError::InvalidSequence))
+    )]
+    #[case::overflow(
+        "xn--99999999999",
+        Err(LabelError::InvalidPunycode(PunyDecodeError::Overflow))
+    )]
+    #[case::ascii("xn--abc-", Err(LabelError::Hyphen))]
+    #[case::control("xn--a", Err(LabelError::InvalidCharacter('\u{80}')))]
+    #[case::not_nfc("xn--bucher-xyd", Err(LabelError::NotNfc))]
+    #[case::disallowed("xn--a-wq7i", Err(LabelError::InvalidCharacter('\u{10FFF}')))]
+    fn test_is_a_label(#[case] input: &str, #[case] expected: Result<(), LabelError>) {
+        assert_eq!(is_a_label(input), expected);
+    }
+
+    #[rstest]
+    #[case::u_label("bücher", Ok(()))]
+    #[case::emoji("🦀", Ok(()))]
+    #[case::empty("", Err(LabelError::Empty))]
+    #[case::ascii("books", Err(LabelError::Ascii))]
+    #[case::uppercase("Bücher", Err(LabelError::InvalidCharacter('B')))]
+    #[case::mapped("ｂücher", Err(LabelError::InvalidCharacter('ｂ')))]
+    #[case::underscore("b_ücher", Err(LabelError::InvalidCharacter('_')))]
+    #[case::not_nfc("bu\u{308}cher", Err(LabelError::NotNfc))]
+    #[case::hyphen("bücher-", Err(LabelError::Hyphen))]
+    #[case::reserved("ab--ü", Err(LabelError::Reserved))]
+    #[case::max_len(&format!("{}ü", "a".repeat(55)), Ok(()))]
+    #[case::too_long(&format!("{}ü", "a".repeat(56)), Err(LabelError::TooLong(64)))]
+    fn test_is_u_label(#[case] input: &str, #[case] expected: Result<(), LabelError>) {
+        assert_eq!(is_u_label(input), expected);
+    }
+
+    #[rstest]
+    #[case::u_label("bücher")]
+    #[case::emoji("🦀")]
+    #[case::cjk("中国")]
+    fn test_round_trip(#[case] input: &str) {
+        assert_eq!(is_u_label(input), Ok(()));
+        let a_label = label_to_ascii(input).unwrap();
+        assert_eq!(is_a_label(&a_label), Ok(()));
+        assert_eq!(is_ldh_label(&a_label), Err(LabelError::Reserved));
+    }
+}
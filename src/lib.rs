@@ -2,14 +2,10 @@
 #![cfg_attr(feature = "forbid-unsafe", forbid(unsafe_code))]
 #![warn(clippy::undocumented_unsafe_blocks, clippy::pedantic, clippy::nursery)]
 
-#[cfg(not(feature = "forbid-unsafe"))]
-use core::hint::unreachable_unchecked;
-
 extern crate alloc;
-use alloc::{borrow::Cow, string::String};
+use alloc::{borrow::Cow, string::String, vec::Vec};
 
 use thiserror::Error;
-use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 
 pub(crate) mod data {
     include!(concat!(env!("OUT_DIR"), "/data.rs"));
@@ -18,17 +14,68 @@ pub(crate) mod data {
 mod mapping;
 pub use mapping::Mapping;
 
+mod parse;
+pub use parse::ParseError;
+
+pub mod table;
+pub use table::{CharPolicy, MappingTable, NoPolicy, Table};
+
+mod processor;
+pub use processor::{Processor, TrailingDot};
+
+mod info;
+pub use info::{char_info, CharInfo};
+
+mod chars;
+pub use chars::{map_chars, MapChars};
+
+mod fold;
+pub use fold::{fold, OnDisallowed};
+
+mod lossy;
+pub use lossy::{to_unicode_lossy, ErrorSpan, Errors};
+
+mod process;
+pub use process::{process, LabelSpans, Processed};
+
+mod label;
+pub use label::{
+    is_a_label, is_ldh_label, is_u_label, label_to_ascii, label_to_unicode, labels, validate_label,
+    Label, LabelError, LabelKind, Labels,
+};
+
+pub mod domain;
+pub use domain::{AsciiDomain, UnicodeDomain};
+
+mod ascii;
+pub mod batch;
+pub mod bootstring;
+#[cfg(feature = "std")]
+pub mod cache;
+mod pipeline;
 pub mod punycode;
+#[cfg(feature = "std")]
+pub use cache::{Cache, SyncCache};
 
 /// The prefix used before a punycode label.
 pub const PREFIX: &str = "xn--";
 
+/// The version of Unicode the built-in mapping table is taken from, as `(major, minor, update)`.
+pub const UNICODE_VERSION: (u8, u8, u8) = data::UNICODE_VERSION;
+
+/// The version of Unicode the normalization data is taken from, as `(major, minor, update)`.
+///
+/// This may be newer than [`UNICODE_VERSION`], which is harmless since the normalization of
+/// assigned characters never changes. The build fails if it is older.
+pub const NORMALIZATION_UNICODE_VERSION: (u8, u8, u8) = unicode_normalization::UNICODE_VERSION;
+
 const PUNYCODE_PREFIX: u32 =
     ((b'-' as u32) << 24) | ((b'-' as u32) << 16) | ((b'N' as u32) << 8) | b'X' as u32;
 const PUNYCODE_PREFIX_MASK: u32 = (0xFF << 24) | (0xFF << 16) | (0xDF << 8) | 0xDF;
 
 /// `has_punycode_prefix` from idna
 /// Copyright (c) 2013-2022 The rust-url developers
+#[allow(clippy::many_single_char_names)]
 const fn has_punycode_prefix(slice: &[u8]) -> bool {
     if slice.len() < 4 {
         return false;
@@ -76,191 +123,255 @@ macro_rules! _assert {
 }
 pub(crate) use _assert;
 
-macro_rules! _unreachable {
-    () => {{
-        #[cfg(feature = "forbid-unsafe")]
-        {
-            unreachable!()
-        }
-        #[cfg(not(feature = "forbid-unsafe"))]
-        #[allow(unused_unsafe, reason = "may be encased in an existing unsafe block")]
-        {
-            use core::hint::unreachable_unchecked;
-            // SAFETY: Caller must verify this is safe.
-            unsafe { unreachable_unchecked() }
-        }
-    }};
-
-    ($e:expr) => {{
-        #[cfg(feature = "forbid-unsafe")]
-        {
-            unreachable!($e)
-        }
-        #[cfg(not(feature = "forbid-unsafe"))]
-        #[allow(unused_unsafe, reason = "may be encased in an existing unsafe block")]
-        {
-            use core::hint::unreachable_unchecked;
-            // SAFETY: Caller must verify this is safe.
-            unsafe { unreachable_unchecked() }
-        }
-    }};
-}
-pub(crate) use _unreachable;
-
 #[derive(Debug, Error, PartialEq, Eq, Clone)]
 pub enum ToAsciiError {
-    #[error("invalid character: {}", Self::format_option(.0))]
+    #[error("invalid character: {}", format_option(*.0))]
     InvalidCharacter(Option<char>),
     #[error("punycode encoding error: {0}")]
     Encode(#[from] punycode::PunyEncodeError),
     #[error("invalid punycode label: {0}")]
     InvalidPunycode(String),
+    #[error("unpaired surrogate: {0:#06X}")]
+    UnpairedSurrogate(u16),
+    /// The character was introduced after the maximum version of Unicode, see
+    /// [`Processor::with_max_age`].
+    #[error("character {0} was introduced in Unicode {1}.{2}")]
+    TooNew(char, u8, u8),
+    /// A label other than the root is empty, see [`Processor::with_empty_labels`].
+    #[error("empty label")]
+    EmptyLabel,
+    /// The domain does not end with a dot, see [`TrailingDot::Require`].
+    #[error("missing trailing dot")]
+    MissingTrailingDot,
 }
 
-impl ToAsciiError {
-    fn format_option(o: &Option<char>) -> String {
-        if let Some(c) = o {
-            String::from(*c)
-        } else {
-            String::new()
-        }
-    }
+/// The error type returned by [`to_unicode`] and friends.
+///
+/// These are the errors of [`ToAsciiError`] except for Punycode encoding errors, which can't
+/// occur when converting to Unicode.
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum ToUnicodeError {
+    #[error("invalid character: {}", format_option(*.0))]
+    InvalidCharacter(Option<char>),
+    #[error("invalid punycode label: {0}")]
+    InvalidPunycode(String),
+    #[error("unpaired surrogate: {0:#06X}")]
+    UnpairedSurrogate(u16),
+    /// See [`ToAsciiError::TooNew`].
+    #[error("character {0} was introduced in Unicode {1}.{2}")]
+    TooNew(char, u8, u8),
+    /// See [`ToAsciiError::EmptyLabel`].
+    #[error("empty label")]
+    EmptyLabel,
+    /// See [`ToAsciiError::MissingTrailingDot`].
+    #[error("missing trailing dot")]
+    MissingTrailingDot,
 }
 
-fn map_internal(mut new: String, old: &str) -> Result<String, ToAsciiError> {
-    for c in old.chars() {
-        // ASCII fast path
-        if c.is_ascii() {
-            new.push(c.to_ascii_lowercase());
-            continue;
-        };
-
-        // SAFETY: All ASCII code points taken care of above.
-        match unwrap!(Mapping::of(c)) {
-            Mapping::Valid => new.push(c),
-            Mapping::Ignored => continue,
-            Mapping::Mapped(s) => new.push_str(s),
-            Mapping::Disallowed => return Err(ToAsciiError::InvalidCharacter(Some(c))),
-            Mapping::Deviation => {
-                #[cfg(not(feature = "forbid-unsafe"))]
-                // SAFETY: Deviations are filtered out in build.rs
-                unsafe {
-                    unreachable_unchecked()
-                };
-                #[cfg(feature = "forbid-unsafe")]
-                unimplemented!("Transitional processing is not implemented.");
-            }
+impl From<ToUnicodeError> for ToAsciiError {
+    fn from(e: ToUnicodeError) -> Self {
+        match e {
+            ToUnicodeError::InvalidCharacter(c) => Self::InvalidCharacter(c),
+            ToUnicodeError::InvalidPunycode(s) => Self::InvalidPunycode(s),
+            ToUnicodeError::UnpairedSurrogate(x) => Self::UnpairedSurrogate(x),
+            ToUnicodeError::TooNew(c, major, minor) => Self::TooNew(c, major, minor),
+            ToUnicodeError::EmptyLabel => Self::EmptyLabel,
+            ToUnicodeError::MissingTrailingDot => Self::MissingTrailingDot,
         }
     }
+}
 
-    Ok(new)
+fn format_option(o: Option<char>) -> String {
+    o.map_or_else(String::new, String::from)
 }
 
-pub fn map_validate(s: &str) -> Result<Cow<str>, ToAsciiError> {
-    // indexing doesn't seem to harm us here - maybe the bounds check gets optimized out
+/// Applies the UTS #46 mapping step to `s`.
+///
+/// Returns [`Cow::Borrowed`] if `s` contains no uppercase ASCII and only valid non-ASCII
+/// characters.
+///
+/// # Errors
+///
+/// `s` contains a disallowed character.
+pub fn map_validate(s: &str) -> Result<Cow<'_, str>, ToAsciiError> {
+    Processor::new().map_validate(s)
+}
 
-    // ASCII fast path
-    if s.is_ascii() {
-        return Ok(Cow::Owned(s.to_ascii_lowercase()));
-    }
+/// Checks that `s` is already mapped, i.e. that [`map_validate`] would not change it.
+///
+/// # Errors
+///
+/// `s` contains uppercase ASCII or a character which is not valid.
+pub fn validate(s: &str) -> Result<(), ToAsciiError> {
+    Processor::new().validate(s)
+}
 
-    for (i, c) in s.char_indices() {
-        // ASCII fast paths
-        if c.is_ascii_uppercase() {
-            let mut new = String::with_capacity(s.len() * 4);
-            new.push_str(&s[..i]);
-            new.push(c.to_ascii_lowercase());
-            return map_internal(new, &s[i + 1..]).map(Cow::Owned);
-        }
-        if c.is_ascii() {
-            continue;
-        }
+/// Returns `true` if `s` is already in the form returned by [`to_ascii`].
+///
+/// This only checks that `s` is ASCII without uppercase letters, which is much cheaper than a
+/// full conversion.
+///
+/// # Examples
+///
+/// ```
+/// assert!(edna::is_canonical_ascii("www.xn--mnchen-3ya.de"));
+/// assert!(!edna::is_canonical_ascii("www.München.de"));
+/// assert!(!edna::is_canonical_ascii("WWW.example.com"));
+/// ```
+#[must_use]
+pub fn is_canonical_ascii(s: &str) -> bool {
+    ascii::lowercase_len(s.as_bytes()) == s.len()
+}
 
-        // SAFETY: All ASCII code points taken care of above.
-        match unwrap!(Mapping::of(c)) {
-            Mapping::Valid => continue,
-
-            Mapping::Ignored => {
-                let n = c.len_utf8();
-                let mut new = String::with_capacity(s.len() * 4);
-                new.push_str(&s[..i]);
-                return map_internal(new, &s[i + n..]).map(Cow::Owned);
-            }
-
-            Mapping::Mapped(r) => {
-                let n = c.len_utf8();
-                let mut new = String::with_capacity(s.len() * 4);
-                new.push_str(&s[..i]);
-                new.push_str(r);
-                return map_internal(new, &s[i + n..]).map(Cow::Owned);
-            }
-
-            Mapping::Disallowed => return Err(ToAsciiError::InvalidCharacter(Some(c))),
-
-            Mapping::Deviation => {
-                #[cfg(not(feature = "forbid-unsafe"))]
-                // SAFETY: Deviations are filtered out in build.rs
-                unsafe {
-                    unreachable_unchecked()
-                };
-                #[cfg(feature = "forbid-unsafe")]
-                unimplemented!("Transitional processing is not implemented.");
-            }
-        }
-    }
+/// Converts a domain to its ASCII form, as described by UTS #46.
+///
+/// Returns [`Cow::Borrowed`] if `s` is already in ASCII form, see [`is_canonical_ascii`].
+///
+/// A trailing dot is kept, and empty labels are allowed, so the empty string is accepted as well.
+/// See [`Processor::with_trailing_dot`] and [`Processor::with_empty_labels`] to change this.
+///
+/// # Errors
+///
+/// - `s` contains a disallowed character.
+/// - A non-ASCII label starts with the Punycode prefix.
+/// - Punycode encoding failed.
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+///
+/// assert_eq!(edna::to_ascii("münchen.de").as_deref(), Ok("xn--mnchen-3ya.de"));
+/// assert!(matches!(edna::to_ascii("example.com"), Ok(Cow::Borrowed(_))));
+/// ```
+pub fn to_ascii(s: &str) -> Result<Cow<'_, str>, ToAsciiError> {
+    Processor::new().to_ascii(s)
+}
 
-    Ok(Cow::Borrowed(s))
+/// Converts a domain to its Unicode form, as described by UTS #46.
+///
+/// Returns [`Cow::Borrowed`] if `s` is already mapped and normalized, and contains no Punycode
+/// labels.
+///
+/// # Errors
+///
+/// - `s` contains a disallowed character.
+/// - A label starting with the Punycode prefix is not valid Punycode, or decodes to an invalid
+///   label.
+///
+/// # Examples
+///
+/// ```
+/// use std::borrow::Cow;
+///
+/// assert_eq!(edna::to_unicode("xn--mnchen-3ya.de").as_deref(), Ok("münchen.de"));
+/// assert!(matches!(edna::to_unicode("münchen.de"), Ok(Cow::Borrowed(_))));
+/// ```
+pub fn to_unicode(s: &str) -> Result<Cow<'_, str>, ToUnicodeError> {
+    Processor::new().to_unicode(s)
 }
 
-pub fn validate(s: &str) -> Result<(), ToAsciiError> {
-    // ASCII fast_paths
-    if s.chars().any(|x| x.is_ascii_uppercase()) {
-        return Err(ToAsciiError::InvalidCharacter(None));
-    }
-    if s.is_ascii() {
-        return Ok(());
-    }
+fn from_utf16(s: &[u16]) -> Result<String, ToUnicodeError> {
+    char::decode_utf16(s.iter().copied())
+        .collect::<Result<String, _>>()
+        .map_err(|e| ToUnicodeError::UnpairedSurrogate(e.unpaired_surrogate()))
+}
 
-    // All ASCII is valid except uppercase, which fails above.
-    for c in s.chars().filter(|x| !x.is_ascii()) {
-        if Mapping::of(c) == Some(Mapping::Valid) {
-            continue;
-        }
-        return Err(ToAsciiError::InvalidCharacter(Some(c)));
-    }
+/// Like [`to_ascii`], but for UTF-16 input and output.
+///
+/// # Errors
+///
+/// - `s` contains an unpaired surrogate.
+/// - See [`to_ascii`].
+pub fn to_ascii_utf16(s: &[u16]) -> Result<Vec<u16>, ToAsciiError> {
+    Processor::new().to_ascii_utf16(s)
+}
 
-    Ok(())
+/// Like [`to_ascii`], but for UTF-32 input and output.
+///
+/// # Errors
+///
+/// See [`to_ascii`].
+pub fn to_ascii_utf32(s: &[char]) -> Result<Vec<char>, ToAsciiError> {
+    Processor::new().to_ascii_utf32(s)
 }
 
-pub fn to_ascii(s: &str) -> Result<String, ToAsciiError> {
-    let mut s = map_validate(s)?;
+/// Like [`to_unicode`], but for UTF-16 input and output.
+///
+/// # Errors
+///
+/// - `s` contains an unpaired surrogate.
+/// - See [`to_unicode`].
+pub fn to_unicode_utf16(s: &[u16]) -> Result<Vec<u16>, ToUnicodeError> {
+    Processor::new().to_unicode_utf16(s)
+}
 
-    if is_nfc_quick(s.chars()) != IsNormalized::Yes {
-        s = Cow::Owned(s.nfc().collect());
-    }
+/// Like [`to_unicode`], but for UTF-32 input and output.
+///
+/// # Errors
+///
+/// See [`to_unicode`].
+pub fn to_unicode_utf32(s: &[char]) -> Result<Vec<char>, ToUnicodeError> {
+    Processor::new().to_unicode_utf32(s)
+}
 
-    let mut ret = String::with_capacity(s.len() * 4);
-    for (label, dot) in s
-        .split_inclusive('.')
-        .map(|x| x.split_once('.').map_or((x, None), |(a, b)| (a, Some(b))))
-    {
-        if label.is_ascii() {
-            // TODO maybe check punycode
-            ret.push_str(label);
-        } else {
-            if has_punycode_prefix(label.as_bytes()) {
-                return Err(ToAsciiError::InvalidPunycode(label.into()));
-            }
-            ret.push_str(PREFIX);
-            ret.push_str(&punycode::encode(label)?);
-        }
+/// Interprets `s` as UTF-8, as described by UTS #46.
+///
+/// Invalid UTF-8 would be replaced by U+FFFD, which is disallowed, so this is an error.
+fn from_utf8(s: &[u8]) -> Result<&str, ToUnicodeError> {
+    core::str::from_utf8(s)
+        .map_err(|_| ToUnicodeError::InvalidCharacter(Some(char::REPLACEMENT_CHARACTER)))
+}
 
-        if dot.is_some() {
-            ret.push('.');
-        }
+/// Interprets `s` as a string and lowercases it, if it is ASCII.
+///
+/// ASCII is valid UTF-8, so this skips validating `s` separately. Returns [`Cow::Borrowed`] if
+/// `s` is lowercase already.
+fn from_ascii(s: &[u8]) -> Option<Cow<'_, str>> {
+    let n = ascii::lowercase_len(s);
+    if n < s.len() && ascii::ascii_len(&s[n..]) < s.len() - n {
+        return None;
     }
 
-    Ok(ret)
+    #[cfg(feature = "forbid-unsafe")]
+    let s = unwrap!(core::str::from_utf8(s).ok());
+    #[cfg(not(feature = "forbid-unsafe"))]
+    // SAFETY: ASCII is always valid UTF-8.
+    let s = unsafe { core::str::from_utf8_unchecked(s) };
+
+    Some(if n == s.len() {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(ascii::to_lowercase(s))
+    })
+}
+
+/// Like [`to_ascii`], but for unvalidated bytes.
+///
+/// # Errors
+///
+/// - `s` is not valid UTF-8. This is reported as [`ToAsciiError::InvalidCharacter`] with
+///   U+FFFD REPLACEMENT CHARACTER.
+/// - See [`to_ascii`].
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(edna::to_ascii_bytes(b"WWW.Example.COM").as_deref(), Ok("www.example.com"));
+/// ```
+pub fn to_ascii_bytes(s: &[u8]) -> Result<Cow<'_, str>, ToAsciiError> {
+    Processor::new().to_ascii_bytes(s)
+}
+
+/// Like [`to_unicode`], but for unvalidated bytes.
+///
+/// # Errors
+///
+/// - `s` is not valid UTF-8. This is reported as [`ToUnicodeError::InvalidCharacter`] with
+///   U+FFFD REPLACEMENT CHARACTER.
+/// - See [`to_unicode`].
+pub fn to_unicode_bytes(s: &[u8]) -> Result<Cow<'_, str>, ToUnicodeError> {
+    Processor::new().to_unicode_bytes(s)
 }
 
 #[cfg(test)]
@@ -304,7 +415,10 @@ mod tests {
     #[case::valid("www.¡hola!.com.mx", Ok(()))]
     #[case::mapped("www.Ç.com", Err(ToAsciiError::InvalidCharacter(Some('Ç'))))]
     #[case::invalid("\u{10fff}", Err(ToAsciiError::InvalidCharacter(Some('\u{10fff}'))))]
-    #[case::ignored("www.exam\u{00ad}ple.com", Err(ToAsciiError::InvalidCharacter(Some('\u{00ad}'))))]
+    #[case::ignored(
+        "www.exam\u{00ad}ple.com",
+        Err(ToAsciiError::InvalidCharacter(Some('\u{00ad}')))
+    )]
     #[case::deviation("faß.de", Ok(()))]
     fn test_validate(#[case] input: &str, #[case] expected: Result<(), ToAsciiError>) {
         assert_eq!(validate(input), expected);
@@ -319,4 +433,89 @@ mod tests {
     fn test_map(#[case] input: &str, #[case] expected: Result<&str, ToAsciiError>) {
         assert_eq!(map_validate(input).as_deref(), expected.as_deref());
     }
+
+    #[rstest]
+    #[case::ascii("www.example.com", Ok("www.example.com"))]
+    #[case::munchen("xn--mnchen-3ya.de", Ok("münchen.de"))]
+    #[case::uppercase("XN--MNCHEN-3YA.DE", Ok("münchen.de"))]
+    #[case::unicode("München.de", Ok("münchen.de"))]
+    #[case::chinese_simplified("xn--fsqu00a.xn--0zwm56d", Ok("例子.测试"))]
+    #[case::emoji("xn--zs9h.xn--53h", Ok("🦀.☕"))]
+    #[case::empty("", Ok(""))]
+    #[case::ascii_punycode("xn--abc-.com", Err(ToUnicodeError::InvalidPunycode("xn--abc-".into())))]
+    #[case::invalid_punycode("xn--ß.com", Err(ToUnicodeError::InvalidPunycode("xn--ß".into())))]
+    #[case::bad_digit("xn--a!b.com", Err(ToUnicodeError::InvalidPunycode("xn--a!b".into())))]
+    #[case::invalid("\u{10fff}", Err(ToUnicodeError::InvalidCharacter(Some('\u{10fff}'))))]
+    fn test_to_unicode(#[case] input: &str, #[case] expected: Result<&str, ToUnicodeError>) {
+        assert_eq!(to_unicode(input).as_deref(), expected.as_deref());
+    }
+
+    #[rstest]
+    #[case::ascii("www.example.com", true, true, true)]
+    #[case::uppercase("www.Example.com", false, false, false)]
+    #[case::a_label("xn--mnchen-3ya.de", true, false, true)]
+    #[case::u_label("münchen.de", false, true, true)]
+    #[case::mapped("ｍünchen.de", false, false, false)]
+    #[case::not_nfc("mu\u{308}nchen.de", false, false, true)]
+    #[case::empty("", true, true, true)]
+    fn test_borrowed(
+        #[case] input: &str,
+        #[case] ascii: bool,
+        #[case] unicode: bool,
+        #[case] mapped: bool,
+    ) {
+        assert_eq!(is_canonical_ascii(input), ascii);
+        assert_eq!(matches!(map_validate(input), Ok(Cow::Borrowed(_))), mapped);
+        assert_eq!(matches!(to_ascii(input), Ok(Cow::Borrowed(_))), ascii);
+        assert_eq!(matches!(to_unicode(input), Ok(Cow::Borrowed(_))), unicode);
+    }
+
+    #[rstest]
+    #[case::munchen("münchen.de", "xn--mnchen-3ya.de")]
+    #[case::emoji("🦀.☕", "xn--zs9h.xn--53h")]
+    fn test_utf16_utf32(#[case] unicode: &str, #[case] ascii: &str) {
+        let unicode16 = unicode.encode_utf16().collect::<Vec<_>>();
+        let ascii16 = ascii.encode_utf16().collect::<Vec<_>>();
+        assert_eq!(to_ascii_utf16(&unicode16), Ok(ascii16.clone()));
+        assert_eq!(to_unicode_utf16(&ascii16), Ok(unicode16));
+
+        let unicode32 = unicode.chars().collect::<Vec<_>>();
+        let ascii32 = ascii.chars().collect::<Vec<_>>();
+        assert_eq!(to_ascii_utf32(&unicode32), Ok(ascii32.clone()));
+        assert_eq!(to_unicode_utf32(&ascii32), Ok(unicode32));
+
+        assert_eq!(
+            to_ascii_utf16(&[0xD83E, 0x2E]),
+            Err(ToAsciiError::UnpairedSurrogate(0xD83E))
+        );
+    }
+
+    #[rstest]
+    #[case::lowercase(b"www.example.com", Ok("www.example.com"), Ok("www.example.com"))]
+    #[case::ascii(b"WWW.Example.COM", Ok("www.example.com"), Ok("www.example.com"))]
+    #[case::punycode(b"XN--MNCHEN-3YA.de", Ok("xn--mnchen-3ya.de"), Ok("m\u{fc}nchen.de"))]
+    #[case::unicode("München.de".as_bytes(), Ok("xn--mnchen-3ya.de"), Ok("münchen.de"))]
+    #[case::invalid_utf8(
+        b"m\xfcnchen.de",
+        Err(ToAsciiError::InvalidCharacter(Some('\u{fffd}'))),
+        Err(ToUnicodeError::InvalidCharacter(Some('\u{fffd}')))
+    )]
+    fn test_bytes(
+        #[case] input: &[u8],
+        #[case] ascii: Result<&str, ToAsciiError>,
+        #[case] unicode: Result<&str, ToUnicodeError>,
+    ) {
+        assert_eq!(to_ascii_bytes(input).as_deref(), ascii.as_deref());
+        assert_eq!(to_unicode_bytes(input).as_deref(), unicode.as_deref());
+
+        // borrowed exactly when the string functions borrow
+        let is_borrowed = |x| matches!(x, Ok(Cow::Borrowed(_)));
+        if let Ok(s) = core::str::from_utf8(input) {
+            assert_eq!(is_borrowed(to_ascii_bytes(input)), is_borrowed(to_ascii(s)));
+            assert_eq!(
+                is_borrowed(to_unicode_bytes(input).map_err(ToAsciiError::from)),
+                is_borrowed(to_unicode(s).map_err(ToAsciiError::from))
+            );
+        }
+    }
 }
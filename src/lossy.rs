@@ -0,0 +1,157 @@
+use core::{ops::Range, slice};
+
+extern crate alloc;
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::{Processor, ToUnicodeError};
+
+/// An error found by [`to_unicode_lossy`] or [`crate::process`], and the part of the output it
+/// concerns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorSpan<E = ToUnicodeError> {
+    /// The byte range of the output, i.e. a U+FFFD REPLACEMENT CHARACTER or a whole label.
+    pub span: Range<usize>,
+    pub error: E,
+}
+
+/// The errors found by [`to_unicode_lossy`], in the order of their position in the output.
+///
+/// [`crate::process`] reports [`crate::ToAsciiError`]s instead, since it converts to ASCII as
+/// well.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Errors<E = ToUnicodeError>(pub(crate) Vec<ErrorSpan<E>>);
+
+impl<E> Default for Errors<E> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<E> Errors<E> {
+    /// Returns `true` if there were no errors.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of errors.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns an iterator over the errors.
+    pub fn iter(&self) -> slice::Iter<'_, ErrorSpan<E>> {
+        self.0.iter()
+    }
+}
+
+impl<E> IntoIterator for Errors<E> {
+    type Item = ErrorSpan<E>;
+    type IntoIter = vec::IntoIter<ErrorSpan<E>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, E> IntoIterator for &'a Errors<E> {
+    type Item = &'a ErrorSpan<E>;
+    type IntoIter = slice::Iter<'a, ErrorSpan<E>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Converts a domain to its Unicode form like [`crate::to_unicode`], but always returns a string.
+///
+/// As described by UTS #46, processing continues after an error:
+///
+/// - Disallowed characters are replaced by U+FFFD REPLACEMENT CHARACTER.
+/// - Labels which are not valid Punycode, or decode to an invalid label, are left as they are.
+///
+/// Every error is returned along with the part of the output it concerns. If there are none,
+/// the output is the same as that of [`crate::to_unicode`].
+///
+/// # Examples
+///
+/// ```
+/// let (unicode, errors) = edna::to_unicode_lossy("xn--mnchen-3ya.xn--a.\u{10FFF}.de");
+/// assert_eq!(unicode, "münchen.xn--a.\u{FFFD}.de");
+///
+/// let spans = errors.iter().map(|x| &unicode[x.span.clone()]).collect::<Vec<_>>();
+/// assert_eq!(spans, ["xn--a", "\u{FFFD}"]);
+/// ```
+#[must_use]
+pub fn to_unicode_lossy(s: &str) -> (String, Errors) {
+    Processor::new().to_unicode_lossy(s)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::valid("xn--mnchen-3ya.DE", "münchen.de", &[])]
+    #[case::empty("", "", &[])]
+    #[case::disallowed(
+        "a\u{10FFF}b.\u{10FFF}",
+        "a\u{FFFD}b.\u{FFFD}",
+        &[
+            (1..4, ToUnicodeError::InvalidCharacter(Some('\u{10FFF}'))),
+            (6..9, ToUnicodeError::InvalidCharacter(Some('\u{10FFF}'))),
+        ]
+    )]
+    #[case::replacement("\u{FFFD}", "\u{FFFD}", &[(0..3, ToUnicodeError::InvalidCharacter(Some('\u{FFFD}')))])]
+    #[case::punycode(
+        "XN--A.xn--mnchen-3ya",
+        "xn--a.münchen",
+        &[(0..5, ToUnicodeError::InvalidPunycode("xn--a".into()))]
+    )]
+    #[case::both(
+        "xn--a\u{10FFF}",
+        "xn--a\u{FFFD}",
+        &[
+            (0..8, ToUnicodeError::InvalidPunycode("xn--a\u{FFFD}".into())),
+            (5..8, ToUnicodeError::InvalidCharacter(Some('\u{10FFF}'))),
+        ]
+    )]
+    #[case::mapped_dot("a．\u{10FFF}", "a.\u{FFFD}", &[(2..5, ToUnicodeError::InvalidCharacter(Some('\u{10FFF}')))])]
+    fn test_to_unicode_lossy(
+        #[case] input: &str,
+        #[case] expected: &str,
+        #[case] errors: &[(Range<usize>, ToUnicodeError)],
+    ) {
+        let (unicode, got) = to_unicode_lossy(input);
+        assert_eq!(unicode, expected);
+
+        let got = got
+            .into_iter()
+            .map(|x| (x.span, x.error))
+            .collect::<Vec<_>>();
+        assert_eq!(got, errors);
+
+        if errors.is_empty() {
+            assert_eq!(crate::to_unicode(input).as_deref(), Ok(expected));
+        } else {
+            assert!(crate::to_unicode(input).is_err());
+        }
+    }
+
+    #[test]
+    fn test_max_age() {
+        let (unicode, errors) = Processor::new()
+            .with_max_age((9, 0))
+            .to_unicode_lossy("🦒.de");
+        assert_eq!(unicode, "\u{FFFD}.de");
+        assert_eq!(
+            errors.iter().map(|x| &x.error).collect::<Vec<_>>(),
+            [&ToUnicodeError::TooNew('🦒', 10, 0)]
+        );
+    }
+}
@@ -15,22 +15,151 @@ pub enum Mapping<'a> {
 impl Mapping<'_> {
     #[must_use]
     pub fn of(c: char) -> Option<Self> {
-        use crate::data;
+        Self::entry(c).map(|x| x.1)
+    }
+
+    /// Returns the ranges of code points which are mapped to exactly `target`.
+    ///
+    /// This is the reverse of [`Mapping::Mapped`], and includes uppercase ASCII letters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use edna::Mapping;
+    ///
+    /// let preimages = Mapping::preimages("a");
+    /// for c in ['A', 'ª', 'ⓐ', 'Ａ', '𝐚'] {
+    ///     assert!(preimages.iter().any(|x| x.contains(&c)));
+    /// }
+    /// assert!(Mapping::preimages("ab").is_empty());
+    /// ```
+    #[must_use]
+    #[cfg(not(feature = "small-tables"))]
+    pub fn preimages(target: &str) -> &'static [core::ops::RangeInclusive<char>] {
+        use crate::data::PREIMAGES;
+
+        PREIMAGES
+            .binary_search_by(|&(x, _)| x.cmp(target))
+            .map_or(&[], |i| PREIMAGES[i].1)
+    }
+
+    /// Returns the ranges of code points which are mapped to exactly `target`.
+    ///
+    /// This is the reverse of [`Mapping::Mapped`], and includes uppercase ASCII letters.
+    #[must_use]
+    #[cfg(feature = "small-tables")]
+    pub fn preimages(target: &str) -> &'static [core::ops::RangeInclusive<char>] {
         use core::cmp::Ordering;
 
-        data::MAPPING
-            .binary_search_by(|(range, _)| {
-                if range.contains(&c) {
-                    Ordering::Equal
-                } else if c < *range.start() {
-                    Ordering::Greater
-                } else {
-                    Ordering::Less
+        use crate::data::{
+            PREIMAGE_POOL, PREIMAGE_POOL_ENDS, PREIMAGE_RANGES, PREIMAGE_RANGE_ENDS,
+        };
+
+        let start = |ends: &[u16], i: usize| i.checked_sub(1).map_or(0, |x| usize::from(ends[x]));
+
+        let (mut low, mut high) = (0, PREIMAGE_POOL_ENDS.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let x = &PREIMAGE_POOL
+                [start(PREIMAGE_POOL_ENDS, mid)..usize::from(PREIMAGE_POOL_ENDS[mid])];
+            match x.cmp(target) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => {
+                    return &PREIMAGE_RANGES
+                        [start(PREIMAGE_RANGE_ENDS, mid)..usize::from(PREIMAGE_RANGE_ENDS[mid])]
+                }
+            }
+        }
+
+        &[]
+    }
+
+    /// Returns the range containing `c`, its mapping, and whether the output of the mapping is
+    /// inert under NFC.
+    #[cfg(not(feature = "small-tables"))]
+    pub(crate) fn entry(
+        c: char,
+    ) -> Option<(core::ops::RangeInclusive<char>, Mapping<'static>, bool)> {
+        use crate::data;
+
+        // Only search the ranges overlapping the block of 256 code points containing `c`.
+        let block = c as usize >> 8;
+        let start = usize::from(*data::BLOCKS.get(block)?);
+        let end = usize::from(*data::BLOCKS.get(block + 1)?) + 1;
+        let ranges = data::MAPPING.get(start..end.min(data::MAPPING.len()))?;
+
+        let i = ranges.partition_point(|(range, ..)| *range.end() < c);
+        ranges
+            .get(i)
+            .filter(|(range, ..)| range.contains(&c))
+            .cloned()
+    }
+
+    /// Returns the range containing `c`, its mapping, and whether the output of the mapping is
+    /// inert under NFC.
+    #[cfg(feature = "small-tables")]
+    pub(crate) fn entry(
+        c: char,
+    ) -> Option<(core::ops::RangeInclusive<char>, Mapping<'static>, bool)> {
+        use crate::data::{
+            CHECKPOINTS, CHUNK, LEN, MAPPED, NFC_INERT, POOL, POOL_ENDS, STARTS, STATUS,
+        };
+
+        let status = |i: usize| STATUS[i / 4] >> (i % 4 * 2) & 0b11;
+
+        let k = CHECKPOINTS
+            .partition_point(|&(start, ..)| start <= c as u32)
+            .checked_sub(1)?;
+        let (mut start, offset, mut rank) = CHECKPOINTS[k];
+        let mut offset = usize::from(offset);
+        let mut i = k * CHUNK;
+
+        // Walk forward from the checkpoint until the next range starts after `c`.
+        let end = loop {
+            if i + 1 == LEN {
+                break char::MAX as u32;
+            }
+
+            let mut delta = 0;
+            let mut shift = 0;
+            loop {
+                let byte = STARTS[offset];
+                offset += 1;
+                delta |= u32::from(byte & 0x7F) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 {
+                    break;
                 }
-            })
-            .ok()
-            .and_then(|i| data::MAPPING.get(i))
-            .map(|x| x.1)
+            }
+
+            let next = start + delta;
+            if next > c as u32 {
+                break next - 1;
+            }
+
+            rank += u16::from(status(i) == 2);
+            start = next;
+            i += 1;
+        };
+
+        let mapping = match status(i) {
+            0 => Mapping::Valid,
+            1 => Mapping::Ignored,
+            2 => {
+                let target = usize::from(MAPPED[usize::from(rank)]);
+                let from = target
+                    .checked_sub(1)
+                    .map_or(0, |x| usize::from(POOL_ENDS[x]));
+                Mapping::Mapped(&POOL[from..usize::from(POOL_ENDS[target])])
+            }
+            _ => Mapping::Disallowed,
+        };
+        let nfc_inert = NFC_INERT[i / 8] >> (i % 8) & 1 != 0;
+
+        // The range before the surrogates ends at U+D7FF.
+        let end = char::from_u32(end).unwrap_or('\u{D7FF}');
+        Some((char::from_u32(start)?..=end, mapping, nfc_inert))
     }
 }
 
@@ -46,6 +175,37 @@ mod test {
         }
     }
 
+    /// Asserts that every range contains its `char`, and has the same mapping throughout.
+    #[test]
+    fn entry_range() {
+        for c in '\u{80}'..=char::MAX {
+            let (range, mapping, nfc_inert) = Mapping::entry(c).unwrap();
+            assert!(range.contains(&c));
+            for x in [*range.start(), *range.end()] {
+                let (_, m, n) = Mapping::entry(x).unwrap();
+                assert_eq!((m, n), (mapping, nfc_inert));
+            }
+        }
+    }
+
+    /// Asserts that every mapped `char` is found among the preimages of its target.
+    #[test]
+    fn preimages() {
+        for c in '\0'..=char::MAX {
+            let mut buf = [0; 4];
+            let target = match Mapping::of(c) {
+                Some(Mapping::Mapped(target)) => target,
+                None if c.is_ascii_uppercase() => c.to_ascii_lowercase().encode_utf8(&mut buf),
+                _ => continue,
+            };
+            let preimages = Mapping::preimages(target);
+            assert!(preimages.iter().any(|x| x.contains(&c)), "{c:?}");
+            for x in preimages.iter().flat_map(Clone::clone) {
+                assert!(x.is_ascii_uppercase() || Mapping::of(x) == Some(Mapping::Mapped(target)));
+            }
+        }
+    }
+
     /// Asserts that all non-ASCII `char`s are mapped.
     #[test]
     fn of_unicode() {
@@ -0,0 +1,280 @@
+//! Parsing of `IdnaMappingTable.txt`.
+//!
+//! This module is shared between build.rs, which turns the bundled file into static tables, and
+//! [`crate::MappingTable`], which parses a file at runtime.
+
+extern crate alloc;
+use alloc::{string::String, vec, vec::Vec};
+
+use thiserror::Error;
+use unicode_normalization::{char::canonical_combining_class, is_nfc_quick, IsNormalized};
+
+use crate::mapping::Mapping;
+
+/// An error encountered while parsing a mapping table.
+#[derive(Debug, Error, PartialEq, Eq, Clone)]
+pub enum ParseError {
+    #[error("line {line}: missing field")]
+    MissingField { line: usize },
+    #[error("line {line}: invalid code point {value:?}")]
+    InvalidCodePoint { line: usize, value: String },
+    #[error("line {line}: unknown status {status:?}")]
+    UnknownStatus { line: usize, status: String },
+}
+
+/// A range of code points with the same status, as read from one line of the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdnaMap {
+    pub start: char,
+    pub end: char,
+    pub status: String,
+    pub map: String,
+    /// Whether every character this range produces is NFC on its own and never interacts with its
+    /// neighbours during normalization.
+    pub nfc_inert: bool,
+}
+
+impl IdnaMap {
+    /// Returns the mapping of this range for nontransitional processing.
+    pub fn mapping(&self) -> Mapping<'_> {
+        match self.status.as_str() {
+            "mapped" => Mapping::Mapped(&self.map),
+            "ignored" => Mapping::Ignored,
+            "disallowed" => Mapping::Disallowed,
+            // we don't support transitional processing
+            _ => Mapping::Valid,
+        }
+    }
+}
+
+/// A version of Unicode as `(major, minor)`.
+pub type Age = (u8, u8);
+
+/// One line of the table, including the information from its trailing comment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub map: IdnaMap,
+    /// The version of Unicode which introduced the code points, or `None` if they are unassigned.
+    pub age: Option<Age>,
+    /// The code points named by the comment, i.e. the first and last one of a range.
+    pub names: Vec<(char, String)>,
+}
+
+/// Parses a line which is neither empty nor a comment.
+///
+/// Returns `Ok(Err(_))` with the bounds of ranges which are not valid `char`s, i.e. surrogates.
+fn read_line(line: &str, number: usize) -> Result<Result<Line, (u32, u32)>, ParseError> {
+    let missing = || ParseError::MissingField { line: number };
+
+    let (line, comment) = line.split_once('#').unwrap_or((line, ""));
+    let mut iter = line.split(';').map(str::trim);
+
+    let range = iter.next().ok_or_else(missing)?;
+    let (start, end) = range.split_once("..").unwrap_or((range, range));
+
+    let parse = |x: &str| {
+        u32::from_str_radix(x, 16).map_err(|_| ParseError::InvalidCodePoint {
+            line: number,
+            value: x.into(),
+        })
+    };
+    let (a, b) = (parse(start)?, parse(end)?);
+    let (Some(start), Some(end)) = (char::from_u32(a), char::from_u32(b)) else {
+        return Ok(Err((a, b)));
+    };
+
+    let status = match iter.next().ok_or_else(missing)? {
+        // tables before Unicode 15.1 distinguish these for UseSTD3ASCIIRules, which we don't use
+        "disallowed_STD3_valid" => "valid",
+        "disallowed_STD3_mapped" => "mapped",
+        x @ ("valid" | "ignored" | "mapped" | "deviation" | "disallowed") => x,
+        x => {
+            return Err(ParseError::UnknownStatus {
+                line: number,
+                status: x.into(),
+            })
+        }
+    };
+
+    let map = iter
+        .next()
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(|x| {
+            parse(x).and_then(|c| {
+                char::from_u32(c).ok_or_else(|| ParseError::InvalidCodePoint {
+                    line: number,
+                    value: x.into(),
+                })
+            })
+        })
+        .collect::<Result<String, _>>()?;
+
+    let (age, names) = read_comment(comment, start, end);
+
+    Ok(Ok(Line {
+        map: IdnaMap {
+            start,
+            end,
+            status: status.into(),
+            map,
+            nfc_inert: true,
+        },
+        age,
+        names,
+    }))
+}
+
+/// Parses a comment such as `# 1.1  DIGIT ZERO..DIGIT NINE` describing `start..=end`.
+///
+/// Comments are informative only, so anything unexpected is ignored rather than an error.
+fn read_comment(comment: &str, start: char, end: char) -> (Option<Age>, Vec<(char, String)>) {
+    let (age, rest) = comment
+        .trim()
+        .split_once(' ')
+        .unwrap_or_else(|| (comment.trim(), ""));
+    let age = age
+        .split_once('.')
+        .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)));
+
+    let names = match rest.trim().split_once("..") {
+        Some((first, last)) => vec![(start, first), (end, last)],
+        None => vec![(start, rest.trim())],
+    };
+    let names = names
+        .into_iter()
+        // labels such as `<reserved-FBC3>` are not names
+        .filter(|(_, name)| !name.is_empty() && !name.starts_with('<'))
+        .map(|(c, name)| (c, name.into()))
+        .collect();
+
+    (age, names)
+}
+
+fn is_nfc_inert(c: char) -> bool {
+    canonical_combining_class(c) == 0 && is_nfc_quick(core::iter::once(c)) == IsNormalized::Yes
+}
+
+/// Splits `range` wherever the NFC inertness of its output changes.
+fn split_nfc_inert(range: IdnaMap) -> Vec<IdnaMap> {
+    match range.status.as_str() {
+        "mapped" => {
+            let nfc_inert = range.map.chars().all(is_nfc_inert);
+            vec![IdnaMap { nfc_inert, ..range }]
+        }
+        "valid" | "deviation" => {
+            let mut split: Vec<IdnaMap> = Vec::new();
+            for c in (range.start..=range.end).filter(|c| !c.is_ascii()) {
+                let nfc_inert = is_nfc_inert(c);
+                match split.last_mut() {
+                    Some(last) if last.nfc_inert == nfc_inert => last.end = c,
+                    _ => split.push(IdnaMap {
+                        start: c,
+                        end: c,
+                        status: range.status.clone(),
+                        map: range.map.clone(),
+                        nfc_inert,
+                    }),
+                }
+            }
+            split
+        }
+        _ => vec![range],
+    }
+}
+
+fn merge_ranges(ranges: Vec<IdnaMap>) -> Vec<IdnaMap> {
+    let mut merged: Vec<IdnaMap> = Vec::new();
+
+    for range in ranges {
+        if let Some(last) = merged.last_mut() {
+            if overlaps(&range, last) {
+                last.end = range.end.max(last.end);
+                continue;
+            }
+        }
+        merged.push(range);
+    }
+
+    merged
+}
+
+fn overlaps(a: &IdnaMap, b: &IdnaMap) -> bool {
+    let next = char::from_u32(b.end as u32 + 1);
+
+    next.is_some_and(|next| {
+        a.start <= next
+            && a.status == b.status
+            && a.nfc_inert == b.nfc_inert
+            && if a.status == "mapped" {
+                a.map == b.map
+            } else {
+                true
+            }
+    })
+}
+
+/// Returns the Unicode version stated in the header of a data file, e.g. `# Version: 16.0.0`.
+pub fn version<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<(u8, u8, u8)> {
+    lines
+        .into_iter()
+        .map_while(|x| x.strip_prefix('#'))
+        .find_map(|x| {
+            let mut parts = x.trim().strip_prefix("Version:")?.trim().split('.');
+            let mut next = || parts.next()?.parse().ok();
+            Some((next()?, next()?, next()?))
+        })
+}
+
+/// Parses every line of a mapping table, in order.
+///
+/// `skipped` is called with every range which is not made of valid `char`s.
+///
+/// # Errors
+///
+/// A line is malformed.
+pub fn read_lines<'a>(
+    lines: impl IntoIterator<Item = &'a str>,
+    mut skipped: impl FnMut(u32, u32),
+) -> Result<Vec<Line>, ParseError> {
+    let mut read = Vec::new();
+
+    for (i, line) in lines.into_iter().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match read_line(line, i + 1)? {
+            Ok(line) => read.push(line),
+            Err((start, end)) => skipped(start, end),
+        }
+    }
+
+    Ok(read)
+}
+
+/// Returns the first code point of every run of lines with the same age, and that age.
+pub fn ages(lines: &[Line]) -> Vec<(char, Option<Age>)> {
+    let mut ages = Vec::<(char, Option<Age>)>::new();
+    for line in lines {
+        if ages.last().is_none_or(|&(_, age)| age != line.age) {
+            ages.push((line.map.start, line.age));
+        }
+    }
+    ages
+}
+
+/// Turns the lines of a mapping table into sorted, merged ranges of non-ASCII code points.
+pub fn ranges(lines: Vec<Line>) -> Vec<IdnaMap> {
+    let mut ranges = Vec::new();
+
+    for Line { map: range, .. } in lines {
+        if !(range.start.is_ascii() && range.end.is_ascii()) {
+            ranges.extend(split_nfc_inert(range));
+        }
+    }
+
+    ranges.sort_by_key(|x| x.start);
+    merge_ranges(ranges)
+}
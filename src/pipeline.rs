@@ -0,0 +1,265 @@
+//! The label-by-label processing pipeline behind [`crate::to_ascii`] and [`crate::to_unicode`].
+//!
+//! The input is mapped one character at a time into a label buffer. Once a label is complete it
+//! is normalized if necessary, then handed to a converter which appends it to the output. No
+//! intermediate copy of the whole domain is ever made, and all buffers can be reused.
+
+use core::ops::RangeInclusive;
+
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::{
+    ascii,
+    punycode::PUNYCODE,
+    table::{lookup, Explain, Table},
+    Mapping, ToAsciiError, ToUnicodeError, PREFIX,
+};
+
+type Utf32 = Vec<char>;
+
+const PREFIX_CHARS: [char; 4] = ['x', 'n', '-', '-'];
+
+/// Buffers which can be reused between runs of the pipeline.
+#[derive(Debug, Default, Clone)]
+pub struct Scratch {
+    /// The mapped label currently being processed.
+    label: Utf32,
+    /// The normalized label, if normalization changed it.
+    nfc: Utf32,
+    /// Scratch space for the converter.
+    tmp: Utf32,
+    /// Whether `label` is known to be NFC without checking.
+    nfc_inert: bool,
+}
+
+/// Runs the pipeline over `s`, calling `convert` on every mapped and normalized label.
+///
+/// Characters are mapped with `table`. `convert` receives the label, a scratch buffer, and the
+/// output to append to. Dots are appended by the pipeline.
+pub fn run<T, F, E>(
+    table: &T,
+    s: &str,
+    scratch: &mut Scratch,
+    out: &mut String,
+    mut convert: F,
+) -> Result<(), E>
+where
+    T: Explain + ?Sized,
+    F: FnMut(&[char], &mut Utf32, &mut String) -> Result<(), E>,
+    E: From<ToUnicodeError>,
+{
+    scratch.label.clear();
+    scratch.label.reserve(s.len());
+    scratch.nfc_inert = true;
+
+    // Neighbouring characters are often from the same range, so remember the last one.
+    let mut last: (RangeInclusive<char>, Mapping, bool) = ('\0'..='\0', Mapping::Disallowed, true);
+
+    let mut rest = s;
+    loop {
+        // ASCII fast path
+        let n = ascii::ascii_len(rest.as_bytes());
+        push_ascii(&rest.as_bytes()[..n], scratch, out, &mut convert)?;
+
+        let mut chars = rest[n..].chars();
+        let Some(c) = chars.next() else {
+            break;
+        };
+        rest = chars.as_str();
+
+        if !last.0.contains(&c) {
+            last = lookup(table, c);
+        }
+
+        scratch.nfc_inert &= last.2;
+
+        match last.1 {
+            Mapping::Valid | Mapping::Deviation => scratch.label.push(c),
+            Mapping::Ignored => (),
+            Mapping::Mapped(r) => {
+                for c in r.chars() {
+                    push(c, scratch, out, &mut convert)?;
+                }
+            }
+            Mapping::Disallowed => return Err(table.disallowed(c).into()),
+        }
+    }
+
+    flush(scratch, out, &mut convert)
+}
+
+/// Pushes a mapped character, completing the current label on a dot.
+#[inline]
+fn push<F, E>(c: char, scratch: &mut Scratch, out: &mut String, convert: &mut F) -> Result<(), E>
+where
+    F: FnMut(&[char], &mut Utf32, &mut String) -> Result<(), E>,
+{
+    if c == '.' {
+        flush(scratch, out, convert)?;
+        out.push('.');
+    } else {
+        scratch.label.push(c);
+    }
+
+    Ok(())
+}
+
+/// Pushes a run of unmapped ASCII, completing labels at every dot.
+#[inline]
+fn push_ascii<F, E>(
+    run: &[u8],
+    scratch: &mut Scratch,
+    out: &mut String,
+    convert: &mut F,
+) -> Result<(), E>
+where
+    F: FnMut(&[char], &mut Utf32, &mut String) -> Result<(), E>,
+{
+    for (i, part) in run.split(|&b| b == b'.').enumerate() {
+        if i > 0 {
+            flush(scratch, out, convert)?;
+            out.push('.');
+        }
+        let lowercase = part.iter().map(|b| char::from(b.to_ascii_lowercase()));
+        scratch.label.extend(lowercase);
+    }
+
+    Ok(())
+}
+
+/// Normalizes and converts the current label.
+fn flush<F, E>(scratch: &mut Scratch, out: &mut String, convert: &mut F) -> Result<(), E>
+where
+    F: FnMut(&[char], &mut Utf32, &mut String) -> Result<(), E>,
+{
+    let Scratch {
+        label,
+        nfc,
+        tmp,
+        nfc_inert,
+    } = scratch;
+
+    // NFC can be applied label by label, since dots never take part in composition.
+    let label = if *nfc_inert || is_nfc_quick(label.iter().copied()) == IsNormalized::Yes {
+        &*label
+    } else {
+        nfc.clear();
+        nfc.extend(label.iter().copied().nfc());
+        &*nfc
+    };
+
+    let ret = convert(label, tmp, out);
+    scratch.label.clear();
+    scratch.nfc_inert = true;
+    ret
+}
+
+/// Returns `true` if `label` starts with the Punycode prefix.
+pub fn has_prefix(label: &[char]) -> bool {
+    label.starts_with(&PREFIX_CHARS)
+}
+
+/// Converts a mapped and normalized label to ASCII.
+pub fn label_to_ascii(
+    label: &[char],
+    tmp: &mut Utf32,
+    out: &mut String,
+) -> Result<(), ToAsciiError> {
+    if label.iter().all(char::is_ascii) {
+        // TODO maybe check punycode
+        out.extend(label);
+        return Ok(());
+    }
+
+    if has_prefix(label) {
+        return Err(ToAsciiError::InvalidPunycode(label.iter().collect()));
+    }
+
+    out.push_str(PREFIX);
+    PUNYCODE.encode_with(label, out, tmp)?;
+
+    Ok(())
+}
+
+/// Converts a mapped and normalized label to Unicode, validating decoded labels with `table`.
+pub fn label_to_unicode<T: Explain + ?Sized>(
+    table: &T,
+    label: &[char],
+    _: &mut Utf32,
+    out: &mut String,
+) -> Result<(), ToUnicodeError> {
+    if !has_prefix(label) {
+        out.extend(label);
+        return Ok(());
+    }
+
+    let invalid = || ToUnicodeError::InvalidPunycode(label.iter().collect());
+
+    if !label.iter().all(char::is_ascii) {
+        return Err(invalid());
+    }
+
+    let decoded = PUNYCODE
+        .decode_utf32(&label[PREFIX_CHARS.len()..])
+        .map_err(|_| invalid())?;
+    if decoded.iter().all(char::is_ascii) {
+        return Err(invalid());
+    }
+    if !is_valid(table, &decoded) {
+        // Characters which are too new are reported as such, as in labels which aren't encoded.
+        let too_new = decoded
+            .iter()
+            .filter(|c| !c.is_ascii())
+            .map(|&c| table.disallowed(c))
+            .find(|e| matches!(e, ToUnicodeError::TooNew(..)));
+        return Err(too_new.unwrap_or_else(invalid));
+    }
+    out.extend(decoded);
+
+    Ok(())
+}
+
+/// Returns `true` if `label` is NFC and consists of characters which are valid in `table` only.
+pub fn is_valid<T: Table + ?Sized>(table: &T, label: &[char]) -> bool {
+    let is_nfc = match is_nfc_quick(label.iter().copied()) {
+        IsNormalized::Yes => true,
+        IsNormalized::No => false,
+        IsNormalized::Maybe => label.iter().copied().nfc().eq(label.iter().copied()),
+    };
+
+    is_nfc
+        && label.iter().all(|&c| {
+            if c.is_ascii() {
+                !c.is_ascii_uppercase()
+            } else {
+                lookup(table, c).1 == Mapping::Valid
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::table::Builtin;
+
+    #[rstest]
+    #[case::ascii("Example.COM", "example.com")]
+    #[case::decomposed("mu\u{308}nchen.de", "xn--mnchen-3ya.de")]
+    #[case::mapped_dot("ｍüｎｃｈｅｎ．de", "xn--mnchen-3ya.de")]
+    #[case::combining_after_dot("a.\u{308}b", "a.xn--b-bcb")]
+    fn test_reuse_scratch(#[case] input: &str, #[case] expected: &str) {
+        let mut scratch = Scratch::default();
+        for _ in 0..2 {
+            let mut out = String::new();
+            run(&Builtin, input, &mut scratch, &mut out, label_to_ascii).unwrap();
+            assert_eq!(out, expected);
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use core::ops::Range;
+
+extern crate alloc;
+use alloc::{string::String, vec::Vec};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{
+    has_punycode_prefix,
+    lossy::{ErrorSpan, Errors},
+    pipeline,
+    table::{lookup, Explain},
+    Mapping, Processor, ToAsciiError, ToUnicodeError,
+};
+
+/// The ASCII and Unicode forms of a domain, computed by [`process`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Processed {
+    /// The ASCII form, as returned by [`crate::to_ascii`].
+    pub ascii: String,
+    /// The Unicode form, as returned by [`crate::to_unicode_lossy`].
+    pub unicode: String,
+    /// The byte ranges of every label in both forms.
+    pub labels: Vec<LabelSpans>,
+    /// The errors found, with spans in [`Self::unicode`].
+    pub errors: Errors<ToAsciiError>,
+}
+
+/// Where a label is found in each form of a [`Processed`] domain.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LabelSpans {
+    /// The byte range in [`Processed::ascii`].
+    pub ascii: Range<usize>,
+    /// The byte range in [`Processed::unicode`].
+    pub unicode: Range<usize>,
+}
+
+impl Processed {
+    /// Returns `true` if there were no errors, i.e. both forms are valid.
+    #[must_use]
+    pub const fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Converts a domain to both its ASCII and Unicode forms at once.
+///
+/// Mapping, normalization and validation are done only once, so this is cheaper than calling
+/// [`crate::to_ascii`] and [`crate::to_unicode`] in turn. Errors don't stop processing, as with
+/// [`crate::to_unicode_lossy`]. If there are none, both forms are the same as those returned by
+/// the separate functions.
+///
+/// # Examples
+///
+/// ```
+/// let processed = edna::process("Bücher.Example");
+/// assert!(processed.is_ok());
+/// assert_eq!(processed.ascii, "xn--bcher-kva.example");
+/// assert_eq!(processed.unicode, "bücher.example");
+///
+/// let first = &processed.labels[0];
+/// assert_eq!(&processed.ascii[first.ascii.clone()], "xn--bcher-kva");
+/// assert_eq!(&processed.unicode[first.unicode.clone()], "bücher");
+/// ```
+#[must_use]
+pub fn process(s: &str) -> Processed {
+    Processor::new().process(s)
+}
+
+/// Like [`process`], but looks characters up in `table`.
+///
+/// The errors of the Unicode form alone are returned as well, for [`crate::to_unicode_lossy`].
+pub fn process_with<T: Explain + ?Sized>(table: &T, s: &str) -> (Processed, Errors) {
+    // ASCII fast path
+    if s.is_ascii() && !s.split('.').any(|x| has_punycode_prefix(x.as_bytes())) {
+        let unicode = s.to_ascii_lowercase();
+        let mut start = 0;
+        let labels = unicode
+            .split('.')
+            .map(|x| {
+                let span = start..start + x.len();
+                start = span.end + 1;
+                LabelSpans {
+                    ascii: span.clone(),
+                    unicode: span,
+                }
+            })
+            .collect();
+        let processed = Processed {
+            ascii: unicode.clone(),
+            unicode,
+            labels,
+            errors: Errors::default(),
+        };
+        return (processed, Errors::default());
+    }
+
+    // Map the whole domain first, since mappings may produce dots.
+    let mut mapped = String::with_capacity(s.len());
+    let mut disallowed = Vec::new();
+    for c in s.chars() {
+        if c.is_ascii() {
+            mapped.push(c.to_ascii_lowercase());
+            continue;
+        }
+
+        match lookup(table, c).1 {
+            Mapping::Valid | Mapping::Deviation => mapped.push(c),
+            Mapping::Ignored => (),
+            Mapping::Mapped(r) => mapped.push_str(r),
+            Mapping::Disallowed => {
+                mapped.push(char::REPLACEMENT_CHARACTER);
+                disallowed.push(table.disallowed(c));
+            }
+        }
+    }
+
+    let mut ret = Processed {
+        ascii: String::with_capacity(mapped.len() * 2),
+        unicode: String::with_capacity(mapped.len()),
+        ..Processed::default()
+    };
+    let Processed {
+        ascii,
+        unicode,
+        labels,
+        errors,
+    } = &mut ret;
+    let mut unicode_errors = Vec::new();
+    let mut disallowed = disallowed.into_iter();
+    let (mut label, mut tmp) = (Vec::new(), Vec::new());
+
+    for (i, part) in mapped.split('.').enumerate() {
+        if i > 0 {
+            ascii.push('.');
+            unicode.push('.');
+        }
+        let (a, u) = (ascii.len(), unicode.len());
+
+        label.clear();
+        label.extend(part.nfc());
+        let to_unicode = pipeline::label_to_unicode(table, &label, &mut tmp, unicode);
+        if to_unicode.is_err() {
+            unicode.truncate(u);
+            unicode.extend(&label);
+        }
+        let to_ascii = pipeline::label_to_ascii(&label, &mut tmp, ascii);
+        if to_ascii.is_err() {
+            ascii.truncate(a);
+            ascii.extend(&label);
+        }
+
+        // Both fail with the same error for a Punycode prefix followed by non-ASCII.
+        let span = u..unicode.len();
+        match (to_unicode, to_ascii) {
+            (Err(error), _) => push_error(errors, &mut unicode_errors, span, error),
+            (Ok(()), Err(error)) => errors.0.push(ErrorSpan { span, error }),
+            (Ok(()), Ok(())) => (),
+        }
+
+        // A replacement character never composes with its neighbours, so they stay in order.
+        for (j, c) in unicode[u..].char_indices() {
+            if c == char::REPLACEMENT_CHARACTER {
+                let error = disallowed
+                    .next()
+                    .unwrap_or(ToUnicodeError::InvalidCharacter(None));
+                let span = u + j..u + j + c.len_utf8();
+                push_error(errors, &mut unicode_errors, span, error);
+            }
+        }
+
+        labels.push(LabelSpans {
+            ascii: a..ascii.len(),
+            unicode: u..unicode.len(),
+        });
+    }
+
+    (ret, Errors(unicode_errors))
+}
+
+/// Records an error of the Unicode form, which is an error of the ASCII form as well.
+pub fn push_error(
+    errors: &mut Errors<ToAsciiError>,
+    unicode_errors: &mut Vec<ErrorSpan>,
+    span: Range<usize>,
+    error: ToUnicodeError,
+) {
+    errors.0.push(ErrorSpan {
+        span: span.clone(),
+        error: error.clone().into(),
+    });
+    unicode_errors.push(ErrorSpan { span, error });
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::ascii("Example.COM")]
+    #[case::canonical("example.com")]
+    #[case::empty("")]
+    #[case::trailing_dot("example.com.")]
+    #[case::unicode("Bücher.Example")]
+    #[case::punycode("xn--mnchen-3ya.de")]
+    #[case::mixed("ｍüｎｃｈｅｎ．xn--bcher-kva.de")]
+    #[case::deviation("faß.de")]
+    #[case::decomposed("mu\u{308}nchen")]
+    #[case::disallowed("a\u{10FFF}.de")]
+    #[case::fake_a_label("xn--a.de")]
+    #[case::invalid_a_label("xn--ü.de")]
+    fn test_process(#[case] input: &str) {
+        let processed = process(input);
+
+        let to_ascii = crate::to_ascii(input);
+        let to_unicode = crate::to_unicode(input);
+        assert_eq!(processed.is_ok(), to_ascii.is_ok() && to_unicode.is_ok());
+        if let Ok(ascii) = to_ascii {
+            assert_eq!(processed.ascii, ascii);
+        }
+        if let Ok(unicode) = to_unicode {
+            assert_eq!(processed.unicode, unicode);
+        }
+
+        let labels = processed.labels.iter();
+        let ascii = labels.clone().map(|x| &processed.ascii[x.ascii.clone()]);
+        assert!(ascii.eq(processed.ascii.split('.')));
+        let unicode = labels.map(|x| &processed.unicode[x.unicode.clone()]);
+        assert!(unicode.eq(processed.unicode.split('.')));
+    }
+
+    #[test]
+    fn test_ascii_error() {
+        // too long to encode, but valid in Unicode
+        let input = "a".repeat(30000) + "\u{30000}";
+        let processed = process(&input);
+        assert!(matches!(
+            processed
+                .errors
+                .iter()
+                .map(|x| &x.error)
+                .collect::<Vec<_>>()[..],
+            [ToAsciiError::Encode(_)]
+        ));
+        assert!(crate::to_unicode_lossy(&input).1.is_empty());
+    }
+
+    #[test]
+    fn test_labels() {
+        let processed = process("Bücher.xn--mnchen-3ya");
+        assert_eq!(
+            processed.labels,
+            [
+                LabelSpans {
+                    ascii: 0..13,
+                    unicode: 0..7,
+                },
+                LabelSpans {
+                    ascii: 14..28,
+                    unicode: 8..16,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,980 @@
+//! Domain processing on top of a [`Table`].
+
+use core::ops::{Range, RangeInclusive};
+
+extern crate alloc;
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::{
+    ascii,
+    fold::{self, OnDisallowed},
+    from_ascii, from_utf16, from_utf8, has_punycode_prefix, is_canonical_ascii,
+    label::{labels, Label},
+    lossy::Errors,
+    pipeline::{self, Scratch},
+    process::{self, Processed},
+    table::{lookup, Builtin, CharPolicy, Explain, NoPolicy, Table},
+    Mapping, ToAsciiError, ToUnicodeError,
+};
+
+/// What a [`Processor`] does with the dot at the end of a fully qualified domain.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TrailingDot {
+    /// Keeps a trailing dot if there is one.
+    #[default]
+    Keep,
+    /// Removes a trailing dot, e.g. for certificates which name domains without it.
+    Strip,
+    /// Fails with [`ToAsciiError::MissingTrailingDot`] if there is no trailing dot, e.g. for DNS
+    /// zone files.
+    Require,
+}
+
+/// Converts domains as described by UTS #46, looking characters up in a [`Table`].
+///
+/// The free functions such as [`crate::to_ascii`] use the [`Builtin`] table. Use
+/// [`Processor::with_table`] to process domains with a [`crate::MappingTable`] instead, and
+/// [`Processor::with_policy`] to override the mapping of individual characters.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Processor<T = Builtin, P = NoPolicy> {
+    table: T,
+    policy: P,
+    max_age: Option<(u8, u8)>,
+    trailing_dot: TrailingDot,
+    reject_empty_labels: bool,
+    root: bool,
+}
+
+impl Processor {
+    /// Creates a processor using the [`Builtin`] table.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_table(Builtin)
+    }
+}
+
+impl<T: Table> Processor<T> {
+    /// Creates a processor looking characters up in `table`.
+    #[must_use]
+    pub const fn with_table(table: T) -> Self {
+        Self {
+            table,
+            policy: NoPolicy,
+            max_age: None,
+            trailing_dot: TrailingDot::Keep,
+            reject_empty_labels: false,
+            root: false,
+        }
+    }
+}
+
+impl<T: Table, P: CharPolicy> Processor<T, P> {
+    /// Consults `policy` for every non-ASCII character after looking it up in the table.
+    ///
+    /// Mappings changed by the policy take precedence over [`Self::with_max_age`]. Characters it
+    /// disallows are reported as [`ToAsciiError::InvalidCharacter`], and it applies to the labels
+    /// decoded by [`Self::to_unicode`] as well. See [`CharPolicy`] for an example.
+    #[must_use]
+    pub fn with_policy<Q: CharPolicy>(self, policy: Q) -> Processor<T, Q> {
+        Processor {
+            table: self.table,
+            policy,
+            max_age: self.max_age,
+            trailing_dot: self.trailing_dot,
+            reject_empty_labels: self.reject_empty_labels,
+            root: self.root,
+        }
+    }
+
+    /// Disallows non-ASCII characters introduced after the given version of Unicode, as
+    /// `(major, minor)`.
+    ///
+    /// Such characters are reported as [`ToAsciiError::TooNew`] along with the version which
+    /// introduced them. This keeps characters which fonts may not support yet out of domains.
+    ///
+    /// Ages come from [`Table::age`], and characters it knows no age for count as too new.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use edna::{Processor, ToAsciiError};
+    ///
+    /// let processor = Processor::new().with_max_age((9, 0));
+    /// assert_eq!(processor.to_ascii("🦀.rs").as_deref(), Ok("xn--zs9h.rs"));
+    /// assert_eq!(processor.to_ascii("🦒.rs"), Err(ToAsciiError::TooNew('🦒', 10, 0)));
+    /// ```
+    #[must_use]
+    pub fn with_max_age(self, max_age: (u8, u8)) -> Self {
+        Self {
+            max_age: Some(max_age),
+            ..self
+        }
+    }
+
+    /// Sets what to do with the dot at the end of a fully qualified domain.
+    ///
+    /// Only a single trailing dot is affected. Since the dot is checked after mapping, this
+    /// includes characters mapped to a dot, such as U+3002 IDEOGRAPHIC FULL STOP.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use edna::{Processor, ToAsciiError, TrailingDot};
+    ///
+    /// let processor = Processor::new().with_trailing_dot(TrailingDot::Strip);
+    /// assert_eq!(processor.to_ascii("münchen.de。").as_deref(), Ok("xn--mnchen-3ya.de"));
+    ///
+    /// let processor = Processor::new().with_trailing_dot(TrailingDot::Require);
+    /// assert_eq!(processor.to_ascii("example.com."), Ok("example.com.".into()));
+    /// assert_eq!(processor.to_ascii("example.com"), Err(ToAsciiError::MissingTrailingDot));
+    /// ```
+    #[must_use]
+    pub fn with_trailing_dot(self, trailing_dot: TrailingDot) -> Self {
+        Self {
+            trailing_dot,
+            ..self
+        }
+    }
+
+    /// Sets whether labels may be empty, as in `a..b` or the empty domain.
+    ///
+    /// They are allowed by default, and rejected with [`ToAsciiError::EmptyLabel`] otherwise. This
+    /// also applies to the label functions, such as [`Self::label_to_ascii`]. The empty root label
+    /// after a single trailing dot is always allowed, whatever the [`TrailingDot`] mode.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use edna::{Processor, ToAsciiError};
+    ///
+    /// let processor = Processor::new().with_empty_labels(false);
+    /// assert_eq!(processor.to_ascii("example.com.").as_deref(), Ok("example.com."));
+    /// assert_eq!(processor.to_ascii("example..com"), Err(ToAsciiError::EmptyLabel));
+    /// assert_eq!(processor.to_ascii(""), Err(ToAsciiError::EmptyLabel));
+    /// assert_eq!(processor.label_to_ascii(""), Err(ToAsciiError::EmptyLabel));
+    /// ```
+    #[must_use]
+    pub fn with_empty_labels(self, allowed: bool) -> Self {
+        Self {
+            reject_empty_labels: !allowed,
+            ..self
+        }
+    }
+
+    /// Sets whether a lone dot is the root domain, rather than two empty labels.
+    ///
+    /// The root is accepted even if empty labels are not, see [`Self::with_empty_labels`].
+    /// [`TrailingDot::Strip`] turns it into the empty string, and [`TrailingDot::Require`] is
+    /// satisfied by it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use edna::{Processor, ToAsciiError};
+    ///
+    /// let processor = Processor::new().with_empty_labels(false);
+    /// assert_eq!(processor.to_ascii("."), Err(ToAsciiError::EmptyLabel));
+    /// assert_eq!(processor.with_root(true).to_ascii(".").as_deref(), Ok("."));
+    /// ```
+    #[must_use]
+    pub fn with_root(self, root: bool) -> Self {
+        Self { root, ..self }
+    }
+
+    /// Returns the table used by this processor.
+    #[must_use]
+    pub const fn table(&self) -> &T {
+        &self.table
+    }
+
+    /// Returns the policy used by this processor.
+    #[must_use]
+    pub const fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    /// Returns the table with the options of this processor applied.
+    const fn view(&self) -> View<'_, T, P> {
+        View {
+            table: &self.table,
+            policy: &self.policy,
+            max_age: self.max_age,
+        }
+    }
+
+    /// Checks the output of a conversion against the options for trailing dots, the root and
+    /// empty labels.
+    ///
+    /// Returns the length of `s` to keep, or the error and the part of `s` it concerns.
+    fn check_dots(&self, s: &str) -> Result<usize, (Range<usize>, ToUnicodeError)> {
+        let strip = self.trailing_dot == TrailingDot::Strip;
+
+        if self.root && s == "." {
+            return Ok(if strip { 0 } else { s.len() });
+        }
+
+        let body = s.strip_suffix('.');
+        if self.trailing_dot == TrailingDot::Require && body.is_none() {
+            return Err((s.len()..s.len(), ToUnicodeError::MissingTrailingDot));
+        }
+        let body = body.unwrap_or(s);
+
+        if self.reject_empty_labels {
+            if let Some(label) = labels(body).find(Label::is_empty) {
+                return Err((label.span(), ToUnicodeError::EmptyLabel));
+            }
+        }
+
+        Ok(if strip { body.len() } else { s.len() })
+    }
+
+    /// Applies [`Self::check_dots`] to the output of a conversion.
+    fn apply_dots<'a>(&self, s: Cow<'a, str>) -> Result<Cow<'a, str>, ToUnicodeError> {
+        let len = self.check_dots(&s).map_err(|(_, e)| e)?;
+        Ok(match s {
+            Cow::Borrowed(s) => Cow::Borrowed(&s[..len]),
+            Cow::Owned(mut s) => {
+                s.truncate(len);
+                Cow::Owned(s)
+            }
+        })
+    }
+
+    fn map_internal(&self, mut new: String, mut old: &str) -> Result<String, ToUnicodeError> {
+        loop {
+            // ASCII fast path
+            let n = ascii::ascii_len(old.as_bytes());
+            let start = new.len();
+            new.push_str(&old[..n]);
+            ascii::make_lowercase_str(&mut new[start..]);
+
+            let mut chars = old[n..].chars();
+            let Some(c) = chars.next() else {
+                break;
+            };
+            old = chars.as_str();
+
+            match lookup(&self.view(), c).1 {
+                Mapping::Valid | Mapping::Deviation => new.push(c),
+                Mapping::Ignored => (),
+                Mapping::Mapped(s) => new.push_str(s),
+                Mapping::Disallowed => return Err(self.view().disallowed(c)),
+            }
+        }
+
+        Ok(new)
+    }
+
+    /// Like [`crate::map_validate`], but with this processor's table.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::map_validate`].
+    pub fn map_validate<'a>(&self, s: &'a str) -> Result<Cow<'a, str>, ToAsciiError> {
+        Ok(self.map(s)?)
+    }
+
+    /// Like [`Self::map_validate`], but fails with the error type of [`Self::to_unicode`].
+    fn map<'a>(&self, s: &'a str) -> Result<Cow<'a, str>, ToUnicodeError> {
+        // indexing doesn't seem to harm us here - maybe the bounds check gets optimized out
+
+        // ASCII fast paths
+        if ascii::lowercase_len(s.as_bytes()) == s.len() {
+            return Ok(Cow::Borrowed(s));
+        }
+        if ascii::ascii_len(s.as_bytes()) == s.len() {
+            return Ok(Cow::Owned(ascii::to_lowercase(s)));
+        }
+
+        let mut i = 0;
+        loop {
+            // ASCII fast paths
+            i += ascii::lowercase_len(&s.as_bytes()[i..]);
+            let Some(c) = s[i..].chars().next() else {
+                break;
+            };
+            if c.is_ascii_uppercase() {
+                let mut new = String::with_capacity(s.len() * 4);
+                new.push_str(&s[..i]);
+                return self.map_internal(new, &s[i..]).map(Cow::Owned);
+            }
+            let len = c.len_utf8();
+
+            match lookup(&self.view(), c).1 {
+                Mapping::Valid | Mapping::Deviation => (),
+
+                Mapping::Ignored => {
+                    let mut new = String::with_capacity(s.len() * 4);
+                    new.push_str(&s[..i]);
+                    return self.map_internal(new, &s[i + len..]).map(Cow::Owned);
+                }
+
+                Mapping::Mapped(r) => {
+                    let mut new = String::with_capacity(s.len() * 4);
+                    new.push_str(&s[..i]);
+                    new.push_str(r);
+                    return self.map_internal(new, &s[i + len..]).map(Cow::Owned);
+                }
+
+                Mapping::Disallowed => return Err(self.view().disallowed(c)),
+            }
+
+            i += len;
+        }
+
+        Ok(Cow::Borrowed(s))
+    }
+
+    /// Like [`crate::validate`], but with this processor's table.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::validate`].
+    pub fn validate(&self, s: &str) -> Result<(), ToAsciiError> {
+        // ASCII fast_paths
+        if ascii::has_uppercase(s.as_bytes()) {
+            return Err(ToAsciiError::InvalidCharacter(None));
+        }
+        if ascii::ascii_len(s.as_bytes()) == s.len() {
+            return Ok(());
+        }
+
+        // All ASCII is valid except uppercase, which fails above.
+        let view = self.view();
+        for c in s.chars().filter(|x| !x.is_ascii()) {
+            match lookup(&view, c).1 {
+                Mapping::Valid => (),
+                Mapping::Disallowed => return Err(view.disallowed(c).into()),
+                _ => return Err(ToAsciiError::InvalidCharacter(Some(c))),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Like [`crate::to_ascii`], but with this processor's table.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::to_ascii`].
+    pub fn to_ascii<'a>(&self, s: &'a str) -> Result<Cow<'a, str>, ToAsciiError> {
+        self.to_ascii_with(s, &mut Scratch::default())
+    }
+
+    /// Like [`Self::to_ascii`], but reuses the buffers in `scratch`.
+    pub(crate) fn to_ascii_with<'a>(
+        &self,
+        s: &'a str,
+        scratch: &mut Scratch,
+    ) -> Result<Cow<'a, str>, ToAsciiError> {
+        let ret = self.convert_to_ascii(s, scratch)?;
+        Ok(self.apply_dots(ret)?)
+    }
+
+    fn convert_to_ascii<'a>(
+        &self,
+        s: &'a str,
+        scratch: &mut Scratch,
+    ) -> Result<Cow<'a, str>, ToAsciiError> {
+        // ASCII fast paths
+        if is_canonical_ascii(s) {
+            return Ok(Cow::Borrowed(s));
+        }
+        if ascii::ascii_len(s.as_bytes()) == s.len() {
+            return Ok(Cow::Owned(ascii::to_lowercase(s)));
+        }
+
+        let mut ret = String::with_capacity(s.len() * 4);
+        pipeline::run(&self.view(), s, scratch, &mut ret, pipeline::label_to_ascii)?;
+
+        Ok(Cow::Owned(ret))
+    }
+
+    /// Like [`crate::to_unicode`], but with this processor's table.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::to_unicode`].
+    pub fn to_unicode<'a>(&self, s: &'a str) -> Result<Cow<'a, str>, ToUnicodeError> {
+        self.to_unicode_with(s, &mut Scratch::default())
+    }
+
+    /// Like [`Self::to_unicode`], but reuses the buffers in `scratch`.
+    pub(crate) fn to_unicode_with<'a>(
+        &self,
+        s: &'a str,
+        scratch: &mut Scratch,
+    ) -> Result<Cow<'a, str>, ToUnicodeError> {
+        self.convert_to_unicode(s, scratch)
+            .and_then(|x| self.apply_dots(x))
+    }
+
+    fn convert_to_unicode<'a>(
+        &self,
+        s: &'a str,
+        scratch: &mut Scratch,
+    ) -> Result<Cow<'a, str>, ToUnicodeError> {
+        let is_unchanged = !s.split('.').any(|x| has_punycode_prefix(x.as_bytes()))
+            && self.validate(s).is_ok()
+            && is_nfc_quick(s.chars()) == IsNormalized::Yes;
+        if is_unchanged {
+            return Ok(Cow::Borrowed(s));
+        }
+
+        let mut ret = String::with_capacity(s.len());
+        let view = self.view();
+        pipeline::run(&view, s, scratch, &mut ret, |label, tmp, out| {
+            pipeline::label_to_unicode(&view, label, tmp, out)
+        })?;
+
+        Ok(Cow::Owned(ret))
+    }
+
+    /// Maps and normalizes a single label, which must not contain dots.
+    fn prepare_label(&self, label: &str) -> Result<Vec<char>, ToUnicodeError> {
+        let mapped = self.map(label)?;
+        if mapped.contains('.') {
+            return Err(ToUnicodeError::InvalidCharacter(Some('.')));
+        }
+
+        Ok(mapped.nfc().collect())
+    }
+
+    /// Like [`crate::label_to_ascii`], but with this processor's table and options.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::label_to_ascii`].
+    pub fn label_to_ascii<'a>(&self, label: &'a str) -> Result<Cow<'a, str>, ToAsciiError> {
+        // ASCII fast path
+        if is_canonical_ascii(label) && !label.contains('.') {
+            return Ok(self.check_label(label.into())?);
+        }
+
+        let label = self.prepare_label(label)?;
+        let mut ret = String::with_capacity(label.len() * 4);
+        pipeline::label_to_ascii(&label, &mut Vec::new(), &mut ret)?;
+
+        Ok(self.check_label(ret.into())?)
+    }
+
+    /// Like [`crate::label_to_unicode`], but with this processor's table and options.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::label_to_unicode`].
+    pub fn label_to_unicode<'a>(&self, label: &'a str) -> Result<Cow<'a, str>, ToUnicodeError> {
+        let is_unchanged = !has_punycode_prefix(label.as_bytes())
+            && self.validate_label(label).is_ok()
+            && is_nfc_quick(label.chars()) == IsNormalized::Yes;
+        if is_unchanged {
+            return self.check_label(label.into());
+        }
+
+        let label = self.prepare_label(label)?;
+        let mut ret = String::with_capacity(label.len());
+        pipeline::label_to_unicode(&self.view(), &label, &mut Vec::new(), &mut ret)?;
+
+        self.check_label(ret.into())
+    }
+
+    /// Like [`crate::validate_label`], but with this processor's table and options.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::validate_label`].
+    pub fn validate_label(&self, label: &str) -> Result<(), ToAsciiError> {
+        self.validate(label)?;
+        if label.contains('.') {
+            return Err(ToAsciiError::InvalidCharacter(Some('.')));
+        }
+        self.check_label(label.into())?;
+
+        Ok(())
+    }
+
+    /// Rejects a converted label if it is empty and [`Self::with_empty_labels`] disallows that.
+    fn check_label<'a>(&self, label: Cow<'a, str>) -> Result<Cow<'a, str>, ToUnicodeError> {
+        if self.reject_empty_labels && label.is_empty() {
+            return Err(ToUnicodeError::EmptyLabel);
+        }
+
+        Ok(label)
+    }
+
+    /// Like [`crate::to_unicode_lossy`], but with this processor's table and options.
+    #[must_use]
+    pub fn to_unicode_lossy(&self, s: &str) -> (String, Errors) {
+        let (processed, errors) = self.process_both(s);
+        (processed.unicode, errors)
+    }
+
+    /// Like [`crate::process`], but with this processor's table and options.
+    #[must_use]
+    pub fn process(&self, s: &str) -> Processed {
+        self.process_both(s).0
+    }
+
+    /// Like [`Self::process`], but also returns the errors of the Unicode form alone.
+    fn process_both(&self, s: &str) -> (Processed, Errors) {
+        let (mut processed, mut unicode_errors) = process::process_with(&self.view(), s);
+
+        match self.check_dots(&processed.unicode) {
+            // Both forms end with the same dot, followed by the empty root label.
+            Ok(len) if len < processed.unicode.len() => {
+                processed.ascii.pop();
+                processed.unicode.pop();
+                processed.labels.pop();
+            }
+            Ok(_) => (),
+            Err((span, error)) => {
+                process::push_error(&mut processed.errors, &mut unicode_errors.0, span, error);
+                processed.errors.0.sort_by_key(|x| x.span.start);
+                unicode_errors.0.sort_by_key(|x| x.span.start);
+            }
+        }
+
+        (processed, unicode_errors)
+    }
+
+    /// Like [`crate::fold`], but with this processor's table and options.
+    ///
+    /// Characters rejected by [`Self::with_max_age`] count as disallowed.
+    #[must_use]
+    pub fn fold<'a>(&self, s: &'a str, on_disallowed: OnDisallowed) -> Cow<'a, str> {
+        fold::fold_with(&self.view(), s, on_disallowed)
+    }
+
+    /// Like [`crate::to_ascii_utf16`], but with this processor's table.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::to_ascii_utf16`].
+    pub fn to_ascii_utf16(&self, s: &[u16]) -> Result<Vec<u16>, ToAsciiError> {
+        self.to_ascii(&from_utf16(s)?)
+            .map(|x| x.encode_utf16().collect())
+    }
+
+    /// Like [`crate::to_ascii_utf32`], but with this processor's table.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::to_ascii_utf32`].
+    pub fn to_ascii_utf32(&self, s: &[char]) -> Result<Vec<char>, ToAsciiError> {
+        self.to_ascii(&s.iter().collect::<String>())
+            .map(|x| x.chars().collect())
+    }
+
+    /// Like [`crate::to_unicode_utf16`], but with this processor's table.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::to_unicode_utf16`].
+    pub fn to_unicode_utf16(&self, s: &[u16]) -> Result<Vec<u16>, ToUnicodeError> {
+        self.to_unicode(&from_utf16(s)?)
+            .map(|x| x.encode_utf16().collect())
+    }
+
+    /// Like [`crate::to_unicode_utf32`], but with this processor's table.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::to_unicode_utf32`].
+    pub fn to_unicode_utf32(&self, s: &[char]) -> Result<Vec<char>, ToUnicodeError> {
+        self.to_unicode(&s.iter().collect::<String>())
+            .map(|x| x.chars().collect())
+    }
+
+    /// Like [`crate::to_ascii_bytes`], but with this processor's table.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::to_ascii_bytes`].
+    pub fn to_ascii_bytes<'a>(&self, s: &'a [u8]) -> Result<Cow<'a, str>, ToAsciiError> {
+        // ASCII fast path
+        if let Some(s) = from_ascii(s) {
+            return Ok(self.apply_dots(s)?);
+        }
+
+        self.to_ascii(from_utf8(s)?)
+    }
+
+    /// Like [`crate::to_unicode_bytes`], but with this processor's table.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::to_unicode_bytes`].
+    pub fn to_unicode_bytes<'a>(&self, s: &'a [u8]) -> Result<Cow<'a, str>, ToUnicodeError> {
+        // ASCII fast path, unless Punycode labels need decoding
+        if !s.split(|&b| b == b'.').any(has_punycode_prefix) {
+            if let Some(s) = from_ascii(s) {
+                return self.apply_dots(s);
+            }
+        }
+
+        self.to_unicode(from_utf8(s)?)
+    }
+}
+
+/// The table of a [`Processor`], with the options affecting the mapping of characters applied.
+struct View<'a, T, P> {
+    table: &'a T,
+    policy: &'a P,
+    max_age: Option<(u8, u8)>,
+}
+
+impl<T: Table, P: CharPolicy> View<'_, T, P> {
+    /// Looks up `c` with the options applied.
+    ///
+    /// Returns the mapping, whether its output is inert under NFC, and the version of Unicode
+    /// which introduced `c` if it is disallowed for being too new only.
+    fn resolve(&self, c: char) -> (Mapping<'_>, bool, Option<(u8, u8)>) {
+        let (_, mapping, nfc_inert) = lookup(self.table, c);
+
+        // The policy has the final say over any mapping it changes. The output of a changed
+        // mapping may need normalizing.
+        if P::ACTIVE {
+            let changed = self.policy.apply(c, mapping);
+            if changed != mapping {
+                return (changed, false, None);
+            }
+        }
+
+        let Some(max_age) = self.max_age else {
+            return (mapping, nfc_inert, None);
+        };
+        if mapping == Mapping::Disallowed {
+            return (mapping, nfc_inert, None);
+        }
+        match self.table.age(c) {
+            Some(age) if age <= max_age => (mapping, nfc_inert, None),
+            age => (Mapping::Disallowed, true, age),
+        }
+    }
+}
+
+impl<T: Table, P: CharPolicy> Table for View<'_, T, P> {
+    #[inline]
+    fn entry(&self, c: char) -> Option<(RangeInclusive<char>, Mapping<'_>, bool)> {
+        if !P::ACTIVE && self.max_age.is_none() {
+            return self.table.entry(c);
+        }
+
+        // Options may treat characters of the same range differently, so don't let the pipeline
+        // reuse ranges.
+        let (mapping, nfc_inert, _) = self.resolve(c);
+        Some((c..=c, mapping, nfc_inert))
+    }
+
+    fn age(&self, c: char) -> Option<(u8, u8)> {
+        self.table.age(c)
+    }
+}
+
+impl<T: Table, P: CharPolicy> Explain for View<'_, T, P> {
+    fn disallowed(&self, c: char) -> ToUnicodeError {
+        match self.resolve(c).2 {
+            Some((major, minor)) => ToUnicodeError::TooNew(c, major, minor),
+            None => ToUnicodeError::InvalidCharacter(Some(c)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use rstest::rstest;
+
+    use super::*;
+    use crate::MappingTable;
+
+    fn runtime() -> &'static MappingTable {
+        static TABLE: std::sync::OnceLock<MappingTable> = std::sync::OnceLock::new();
+        TABLE.get_or_init(|| MappingTable::parse(include_str!("../IdnaMappingTable.txt")).unwrap())
+    }
+
+    #[rstest]
+    #[case::ascii("WWW.Example.COM")]
+    #[case::munchen("München.de")]
+    #[case::mapped_dot("ｍüｎｃｈｅｎ．de")]
+    #[case::decomposed("mu\u{308}nchen.de")]
+    #[case::ignored("www.exam\u{00ad}ple.com")]
+    #[case::deviation("faß.de")]
+    #[case::punycode("xn--mnchen-3ya.de")]
+    #[case::invalid_punycode("xn--a.de")]
+    #[case::invalid("\u{10fff}")]
+    fn test_runtime_table(#[case] input: &str) {
+        let processor = Processor::with_table(runtime());
+        assert_eq!(processor.to_ascii(input), crate::to_ascii(input));
+        assert_eq!(processor.to_unicode(input), crate::to_unicode(input));
+        assert_eq!(processor.map_validate(input), crate::map_validate(input));
+        assert_eq!(processor.validate(input), crate::validate(input));
+    }
+
+    #[test]
+    fn test_custom_table() {
+        let table = MappingTable::parse("00E9 ; valid\n00C9 ; mapped ; 00E9\n").unwrap();
+        let processor = Processor::with_table(table);
+
+        assert_eq!(processor.to_ascii("É.fr").as_deref(), Ok("xn--9ca.fr"));
+        assert_eq!(processor.to_unicode("xn--9ca.fr").as_deref(), Ok("é.fr"));
+        assert_eq!(
+            processor.to_ascii("ü.de"),
+            Err(ToAsciiError::InvalidCharacter(Some('ü')))
+        );
+        assert_eq!(
+            processor.to_unicode("xn--tda.de"),
+            Err(ToUnicodeError::InvalidPunycode("xn--tda".into()))
+        );
+    }
+
+    #[rstest]
+    #[case::old("🦀🦅.rs", Ok(()))]
+    #[case::new("🦀🦒.rs", Err(ToAsciiError::TooNew('🦒', 10, 0)))]
+    #[case::mapped("\u{1C89}.ru", Err(ToAsciiError::TooNew('\u{1C89}', 16, 0)))]
+    #[case::disallowed("\u{10FFF}", Err(ToAsciiError::InvalidCharacter(Some('\u{10FFF}'))))]
+    #[case::disallowed_new("a\u{890}b", Err(ToAsciiError::InvalidCharacter(Some('\u{890}'))))]
+    fn test_max_age(#[case] input: &str, #[case] expected: Result<(), ToAsciiError>) {
+        let processor = Processor::new().with_max_age((9, 0));
+
+        assert_eq!(processor.to_ascii(input).map(|_| ()), expected);
+        assert_eq!(
+            processor
+                .to_unicode(input)
+                .map(|_| ())
+                .map_err(ToAsciiError::from),
+            expected
+        );
+        assert_eq!(processor.map_validate(input).map(|_| ()), expected);
+        assert_eq!(processor.validate(input), expected);
+
+        // decoded labels are checked too, after mapping
+        if let (Ok(ascii), Err(ToAsciiError::TooNew(_, major, minor))) =
+            (crate::to_ascii(input), &expected)
+        {
+            assert!(matches!(
+                processor.to_unicode(&ascii),
+                Err(ToUnicodeError::TooNew(_, x, y)) if (x, y) == (*major, *minor)
+            ));
+        }
+    }
+
+    #[rstest]
+    #[case::keep("a.b.", TrailingDot::Keep, Ok("a.b."))]
+    #[case::keep_none("a.b", TrailingDot::Keep, Ok("a.b"))]
+    #[case::strip("a.b.", TrailingDot::Strip, Ok("a.b"))]
+    #[case::strip_mapped("a.b。", TrailingDot::Strip, Ok("a.b"))]
+    #[case::strip_once("a.b..", TrailingDot::Strip, Ok("a.b."))]
+    #[case::strip_none("a.b", TrailingDot::Strip, Ok("a.b"))]
+    #[case::require("a.b.", TrailingDot::Require, Ok("a.b."))]
+    #[case::require_none("a.b", TrailingDot::Require, Err(ToAsciiError::MissingTrailingDot))]
+    #[case::require_empty("", TrailingDot::Require, Err(ToAsciiError::MissingTrailingDot))]
+    fn test_trailing_dot(
+        #[case] input: &str,
+        #[case] trailing_dot: TrailingDot,
+        #[case] expected: Result<&str, ToAsciiError>,
+    ) {
+        let processor = Processor::new().with_trailing_dot(trailing_dot);
+        assert_eq!(processor.to_ascii(input).as_deref(), expected.as_deref());
+        assert_eq!(
+            processor.to_ascii_bytes(input.as_bytes()).as_deref(),
+            expected.as_deref()
+        );
+        assert_eq!(
+            processor
+                .to_unicode(input)
+                .map_err(ToAsciiError::from)
+                .as_deref(),
+            expected.as_deref()
+        );
+    }
+
+    #[rstest]
+    #[case::fqdn("a.b.", Ok("a.b."))]
+    #[case::empty_label("a..b", Err(ToAsciiError::EmptyLabel))]
+    #[case::leading_dot(".a", Err(ToAsciiError::EmptyLabel))]
+    #[case::two_trailing_dots("a..", Err(ToAsciiError::EmptyLabel))]
+    #[case::mapped_dot("a．．b", Err(ToAsciiError::EmptyLabel))]
+    #[case::ignored("a.\u{AD}.b", Err(ToAsciiError::EmptyLabel))]
+    #[case::empty("", Err(ToAsciiError::EmptyLabel))]
+    #[case::root(".", Err(ToAsciiError::EmptyLabel))]
+    fn test_empty_labels(#[case] input: &str, #[case] expected: Result<&str, ToAsciiError>) {
+        let processor = Processor::new().with_empty_labels(false);
+        assert_eq!(processor.to_ascii(input).as_deref(), expected.as_deref());
+        assert_eq!(
+            processor
+                .to_unicode(input)
+                .map_err(ToAsciiError::from)
+                .as_deref(),
+            expected.as_deref()
+        );
+
+        // allowed by default
+        assert!(Processor::new().to_ascii(input).is_ok());
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::ignored("\u{AD}")]
+    fn test_empty_label(#[case] input: &str) {
+        let processor = Processor::new().with_empty_labels(false);
+        assert_eq!(
+            processor.label_to_ascii(input),
+            Err(ToAsciiError::EmptyLabel)
+        );
+        assert_eq!(
+            processor.label_to_unicode(input),
+            Err(ToUnicodeError::EmptyLabel)
+        );
+        assert_eq!(processor.validate_label(""), Err(ToAsciiError::EmptyLabel));
+
+        // allowed by default
+        assert_eq!(Processor::new().label_to_ascii(input).as_deref(), Ok(""));
+        assert_eq!(Processor::new().label_to_unicode(input).as_deref(), Ok(""));
+        assert_eq!(Processor::new().validate_label(""), Ok(()));
+    }
+
+    #[rstest]
+    #[case::keep(TrailingDot::Keep, true, Ok("."))]
+    #[case::strip(TrailingDot::Strip, true, Ok(""))]
+    #[case::require(TrailingDot::Require, true, Ok("."))]
+    #[case::empty_labels(TrailingDot::Keep, false, Ok("."))]
+    fn test_root(
+        #[case] trailing_dot: TrailingDot,
+        #[case] empty_labels: bool,
+        #[case] expected: Result<&str, ToAsciiError>,
+    ) {
+        let processor = Processor::new()
+            .with_trailing_dot(trailing_dot)
+            .with_empty_labels(empty_labels)
+            .with_root(true);
+        for input in [".", "。"] {
+            assert_eq!(processor.to_ascii(input).as_deref(), expected.as_deref());
+            assert_eq!(
+                processor
+                    .to_unicode(input)
+                    .map_err(ToAsciiError::from)
+                    .as_deref(),
+                expected.as_deref()
+            );
+        }
+        assert_eq!(
+            processor.to_ascii(".."),
+            processor.with_root(false).to_ascii("..")
+        );
+    }
+
+    #[test]
+    fn test_process_dots() {
+        let processor = Processor::new()
+            .with_trailing_dot(TrailingDot::Strip)
+            .with_empty_labels(false);
+
+        let processed = processor.process("Bücher.de.");
+        assert!(processed.is_ok());
+        assert_eq!(
+            (processed.ascii.as_str(), processed.unicode.as_str()),
+            ("xn--bcher-kva.de", "bücher.de")
+        );
+        assert_eq!(processed.labels.len(), 2);
+
+        let processed = processor.process("a\u{10FFF}..b");
+        let errors = processed
+            .errors
+            .iter()
+            .map(|x| (x.span.clone(), x.error.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                (1..4, ToAsciiError::InvalidCharacter(Some('\u{10FFF}'))),
+                (5..5, ToAsciiError::EmptyLabel),
+            ]
+        );
+    }
+
+    /// Disallows emoji, allows U+10FFF, and maps ß to "ss".
+    struct Policy;
+
+    impl CharPolicy for Policy {
+        fn apply<'a>(&'a self, c: char, mapping: Mapping<'a>) -> Mapping<'a> {
+            match c {
+                '\u{1F300}'..='\u{1FAFF}' => Mapping::Disallowed,
+                '\u{10FFF}' => Mapping::Valid,
+                'ß' => Mapping::Mapped("ss"),
+                _ => mapping,
+            }
+        }
+    }
+
+    #[rstest]
+    #[case::unchanged("Bücher.de", Ok("xn--bcher-kva.de"), Ok("bücher.de"))]
+    #[case::disallowed(
+        "🦀.rs",
+        Err(ToAsciiError::InvalidCharacter(Some('🦀'))),
+        Err(ToUnicodeError::InvalidCharacter(Some('🦀')))
+    )]
+    #[case::decoded(
+        "xn--zs9h.rs",
+        Ok("xn--zs9h.rs"),
+        Err(ToUnicodeError::InvalidPunycode("xn--zs9h".into()))
+    )]
+    #[case::allowed("a\u{10FFF}", Ok("xn--a-wq7i"), Ok("a\u{10FFF}"))]
+    #[case::remapped("Straße", Ok("strasse"), Ok("strasse"))]
+    #[case::remapped_combining("ßa\u{301}", Ok("xn--ss-nia"), Ok("ssá"))]
+    fn test_policy(
+        #[case] input: &str,
+        #[case] ascii: Result<&str, ToAsciiError>,
+        #[case] unicode: Result<&str, ToUnicodeError>,
+    ) {
+        let processor = Processor::new().with_policy(Policy);
+        assert_eq!(processor.to_ascii(input).as_deref(), ascii.as_deref());
+        assert_eq!(processor.to_unicode(input).as_deref(), unicode.as_deref());
+    }
+
+    #[test]
+    fn test_policy_mapping() {
+        let processor = Processor::new().with_policy(&Policy);
+        assert_eq!(processor.map_validate("Faß").as_deref(), Ok("fass"));
+        assert_eq!(
+            processor.validate("faß"),
+            Err(ToAsciiError::InvalidCharacter(Some('ß')))
+        );
+        assert_eq!(processor.validate("a\u{10FFF}"), Ok(()));
+        assert_eq!(
+            processor.validate("🦀"),
+            Err(ToAsciiError::InvalidCharacter(Some('🦀')))
+        );
+
+        // policies have the final say over the maximum age
+        let processor = processor.with_max_age((9, 0));
+        assert_eq!(processor.validate("a\u{10FFF}"), Ok(()));
+        assert_eq!(
+            processor.validate("🦀"),
+            Err(ToAsciiError::InvalidCharacter(Some('🦀')))
+        );
+        assert_eq!(
+            processor.to_ascii("🦒"),
+            Err(ToAsciiError::InvalidCharacter(Some('🦒')))
+        );
+        assert_eq!(
+            processor.validate("\u{1C89}"),
+            Err(ToAsciiError::TooNew('\u{1C89}', 16, 0))
+        );
+    }
+
+    #[rstest]
+    #[case::ascii("WWW.Example.COM")]
+    #[case::mapped_dot("ｍüｎｃｈｅｎ．de")]
+    #[case::deviation("faß.de")]
+    #[case::invalid("\u{10fff}")]
+    fn test_no_policy(#[case] input: &str) {
+        let processor = Processor::new().with_policy(NoPolicy);
+        assert_eq!(processor.to_ascii(input), crate::to_ascii(input));
+        assert_eq!(processor.to_unicode(input), crate::to_unicode(input));
+        assert_eq!(processor.map_validate(input), crate::map_validate(input));
+    }
+}
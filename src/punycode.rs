@@ -1,106 +1,40 @@
-use core::cmp::Ordering;
-
 extern crate alloc;
 use alloc::{string::String, vec::Vec};
 
-use thiserror::Error;
-
-use crate::{_assert, _unreachable, unwrap};
-
-// You MUST adjust encode_digit() if any of the constants are modified!
-const BASE: u32 = 36;
-const T_MIN: u32 = 1;
-const T_MAX: u32 = 26;
-const SKEW: u32 = 38;
-const DAMP: u32 = 700;
-const INITIAL_BIAS: u32 = 72;
-const INITIAL_N: u32 = 128;
-const DELIMITER: char = '-';
-
-type Utf32 = Vec<char>;
+use crate::bootstring::{Bootstring, Parameters};
+pub use crate::bootstring::{DecodeError as PunyDecodeError, EncodeError as PunyEncodeError};
+
+/// Punycode as specified in RFC 3492.
+pub const PUNYCODE: Bootstring = Bootstring::new(
+    Parameters {
+        t_min: 1,
+        t_max: 26,
+        skew: 38,
+        damp: 700,
+        initial_bias: 72,
+        initial_n: 128,
+    },
+    b"abcdefghijklmnopqrstuvwxyz0123456789",
+    '-',
+);
 
 /// Encodes Unicode as Punycode.
 ///
 /// Overflow may occur if `input` is longer than 63 characters.
 /// Overflow may result in invalid output, but will never result in Undefined Behavior.
 ///
+/// # Errors
+///
+/// Overflow has occured.
+///
 /// # Examples
 ///
 /// ```
 /// # use edna::punycode;
 /// assert_eq!(punycode::encode("München").as_deref(), Ok("Mnchen-3ya"));
 /// ```
-#[must_use]
-#[expect(clippy::cast_possible_truncation)]
 pub fn encode(input: &str) -> Result<String, PunyEncodeError> {
-    let mut new_input = Utf32::with_capacity(input.len() * 4);
-    let mut output = String::with_capacity(input.len() * 4);
-    let mut non_ascii = Utf32::with_capacity(input.len() * 4);
-
-    for c in input.chars() {
-        new_input.push(c);
-        if c.is_ascii() {
-            output.push(c);
-        } else {
-            non_ascii.push(c);
-        }
-    }
-    let input = new_input;
-
-    non_ascii.sort_unstable();
-    non_ascii.dedup();
-    let mut non_ascii = non_ascii.into_iter();
-
-    let basic_len = output.len() as u32;
-    if basic_len > 0 {
-        output.push(DELIMITER);
-    }
-
-    let mut cp = INITIAL_N;
-    let mut delta = 0;
-    let mut bias = INITIAL_BIAS;
-    let mut processed = basic_len;
-
-    while processed < u32::try_from(input.len()).map_err(|_| PunyEncodeError::Overflow)? {
-        // SAFETY: input always contains a code point >= cp while processed < input.len()
-        let min_cp = unwrap!(non_ascii.next()) as u32;
-        delta += (min_cp - cp)
-            .checked_mul(processed + 1)
-            .ok_or(PunyEncodeError::Overflow)?;
-        cp = min_cp;
-        for &c in &input {
-            let c = c as u32;
-            match c.cmp(&cp) {
-                Ordering::Less => delta += 1,
-                Ordering::Equal => {
-                    let mut q = delta;
-                    for k in (BASE..).step_by(BASE as usize) {
-                        let t = clamped_sub(k, bias);
-                        // mutants test for clamped_sub
-                        // SAFETY: clamped to T_MIN ..= T_MAX
-                        _assert!((T_MIN..=T_MAX).contains(&t));
-
-                        if q < t {
-                            break;
-                        }
-
-                        let value = t + ((q - t) % (BASE - t));
-                        output.push(encode_digit(value));
-                        q = (q - t) / (BASE - t);
-                    }
-                    output.push(encode_digit(q));
-                    bias = adapt(delta, processed + 1, processed == basic_len);
-                    delta = 0;
-                    processed += 1;
-                }
-                Ordering::Greater => (),
-            }
-        }
-        delta += 1;
-        cp += 1;
-    }
-
-    Ok(output)
+    PUNYCODE.encode(input)
 }
 
 /// Decodes Punycode to Unicode.
@@ -117,53 +51,72 @@ pub fn encode(input: &str) -> Result<String, PunyEncodeError> {
 /// # use edna::punycode;
 /// assert_eq!(punycode::decode("Mnchen-3ya"), Ok("München".to_string()));
 /// ```
-#[expect(clippy::cast_possible_truncation)]
 pub fn decode(input: &str) -> Result<String, PunyDecodeError> {
-    let (mut output, mut encoded) = input.rsplit_once('-').map_or_else(
-        || (Utf32::new(), input.chars()),
-        |(l, r)| (l.chars().collect::<Utf32>(), r.chars()),
-    );
-
-    let mut cp = INITIAL_N;
-    let mut i: u32 = 0;
-    let mut bias = INITIAL_BIAS;
-
-    while let Some(mut byte) = encoded.next() {
-        let old_i = i;
-        let mut weight = 1;
-
-        for k in (BASE..).step_by(BASE as usize) {
-            let digit = decode_digit(byte).ok_or(PunyDecodeError::NonAscii)?;
+    PUNYCODE.decode(input)
+}
 
-            let product = digit.checked_mul(weight).ok_or(PunyDecodeError::Overflow)?;
-            i = i.checked_add(product).ok_or(PunyDecodeError::Overflow)?;
+/// Encodes UTF-16 as Punycode.
+///
+/// # Errors
+///
+/// - `input` contains an unpaired surrogate.
+/// - Overflow has occured.
+///
+/// # Examples
+///
+/// ```
+/// # use edna::punycode;
+/// let input = "München".encode_utf16().collect::<Vec<_>>();
+/// let expected = "Mnchen-3ya".encode_utf16().collect::<Vec<_>>();
+/// assert_eq!(punycode::encode_utf16(&input), Ok(expected));
+/// ```
+pub fn encode_utf16(input: &[u16]) -> Result<Vec<u16>, PunyEncodeError> {
+    PUNYCODE.encode_utf16(input)
+}
 
-            let t = clamped_sub(k, bias);
-            // mutants test for clamped_sub
-            // SAFETY: clamped to T_MIN ..= T_MAX
-            _assert!((T_MIN..=T_MAX).contains(&t));
+/// Encodes UTF-32 as Punycode.
+///
+/// # Errors
+///
+/// Overflow has occured.
+pub fn encode_utf32(input: &[char]) -> Result<Vec<char>, PunyEncodeError> {
+    PUNYCODE.encode_utf32(input)
+}
 
-            if digit < t {
-                break;
-            }
+/// Decodes UTF-16 Punycode to UTF-16 Unicode.
+///
+/// # Errors
+///
+/// - `input` contains an unpaired surrogate.
+/// - See [`decode`].
+pub fn decode_utf16(input: &[u16]) -> Result<Vec<u16>, PunyDecodeError> {
+    PUNYCODE.decode_utf16(input)
+}
 
-            weight = weight
-                .checked_mul(BASE - t)
-                .ok_or(PunyDecodeError::Overflow)?;
-            byte = encoded.next().ok_or(PunyDecodeError::InvalidSequence)?;
-        }
-        bias = adapt(i - old_i, output.len() as u32 + 1, old_i == 0);
-        cp = cp
-            .checked_add(i / (output.len() as u32 + 1))
-            .ok_or(PunyDecodeError::Overflow)?;
-        i %= output.len() as u32 + 1;
-        // SAFETY: AFAIK this can never fail, but if that's incorrect please open a bug.
-        let c = unwrap!(char::from_u32(cp));
-        output.insert(i as usize, c);
-        i += 1;
-    }
+/// Decodes UTF-32 Punycode to UTF-32 Unicode.
+///
+/// # Errors
+///
+/// See [`decode`].
+pub fn decode_utf32(input: &[char]) -> Result<Vec<char>, PunyDecodeError> {
+    PUNYCODE.decode_utf32(input)
+}
 
-    Ok(output.into_iter().collect::<String>())
+/// Decodes Punycode bytes to Unicode.
+///
+/// # Errors
+///
+/// - `input` is not ASCII.
+/// - See [`decode`].
+///
+/// # Examples
+///
+/// ```
+/// # use edna::punycode;
+/// assert_eq!(punycode::decode_bytes(b"Mnchen-3ya"), Ok("München".to_string()));
+/// ```
+pub fn decode_bytes(input: &[u8]) -> Result<String, PunyDecodeError> {
+    PUNYCODE.decode_bytes(input)
 }
 
 /// Decodes Punycode to Unicode without input validation.
@@ -189,119 +142,10 @@ pub fn decode(input: &str) -> Result<String, PunyDecodeError> {
 /// # }
 /// ```
 #[cfg(not(feature = "forbid-unsafe"))]
-#[allow(clippy::cast_possible_truncation)]
 #[must_use]
 pub unsafe fn decode_unchecked(input: &str) -> String {
-    let (mut output, mut encoded) = input.rsplit_once('-').map_or_else(
-        || (Utf32::new(), input.chars()),
-        |(l, r)| (l.chars().collect::<Utf32>(), r.chars()),
-    );
-
-    let mut cp = INITIAL_N;
-    let mut i: u32 = 0;
-    let mut bias = INITIAL_BIAS;
-
-    while let Some(mut byte) = encoded.next() {
-        let old_i = i;
-        let mut weight = 1;
-
-        for k in (BASE..).step_by(BASE as usize) {
-            let digit = decode_digit(byte).unwrap_unchecked();
-
-            i += digit * weight;
-
-            let t = clamped_sub(k, bias);
-
-            if digit < t {
-                break;
-            }
-
-            weight *= BASE - t;
-            byte = encoded.next().unwrap_unchecked();
-        }
-        bias = adapt(i - old_i, output.len() as u32 + 1, old_i == 0);
-        cp += i / (output.len() as u32 + 1);
-        i %= output.len() as u32 + 1;
-        let c = char::from_u32_unchecked(cp);
-        output.insert(i as usize, c);
-        i += 1;
-    }
-
-    output.into_iter().collect::<String>()
-}
-
-#[derive(Debug, Error, PartialEq, Eq, Clone)]
-pub enum PunyEncodeError {
-    #[error("overflow")]
-    Overflow,
-}
-
-#[derive(Debug, Error, PartialEq, Eq, Clone)]
-pub enum PunyDecodeError {
-    #[error("invalid punycode sequence")]
-    InvalidSequence,
-    #[error("input should be ASCII Punycode")]
-    NonAscii,
-    #[error("overflow")]
-    Overflow,
-}
-
-#[must_use]
-const fn adapt(mut delta: u32, num_points: u32, first_time: bool) -> u32 {
-    // SAFETY: num_points (processed + 1) is always > 0, even on an empty string.
-    _assert!(num_points > 0);
-
-    delta /= if first_time { DAMP } else { 2 };
-    delta += delta / num_points;
-    let mut k = 0;
-    while delta > (BASE - T_MIN) * T_MAX / 2 {
-        delta /= BASE - T_MIN;
-        k += BASE;
-    }
-    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
-}
-
-#[must_use]
-const fn clamped_sub(k: u32, bias: u32) -> u32 {
-    if k <= bias {
-        T_MIN
-    } else if k >= bias + T_MAX {
-        T_MAX
-    } else {
-        k - bias
-    }
-}
-
-#[must_use]
-const fn decode_digit(c: char) -> Option<u32> {
-    match c {
-        '0'..='9' => Some((c as u8 - b'0' + 26) as u32),
-        'A'..='Z' => Some((c as u8 - b'A') as u32),
-        'a'..='z' => Some((c as u8 - b'a') as u32),
-        _ => None,
-    }
-}
-
-#[must_use]
-#[expect(clippy::cast_possible_truncation)]
-const fn encode_digit(d: u32) -> char {
-    // You MUST adjust this function if any of the constants are modified!
-
-    const _: () = assert!(
-        T_MIN == 1,
-        "encode_digit() should be adjusted when constants are modified"
-    );
-    const MAX: u32 = BASE - T_MIN;
-
-    match d {
-        0..T_MAX => (d as u8 + b'a') as char,
-        T_MAX..=MAX => (d as u8 - 26 + b'0') as char,
-        _ => {
-            // SAFETY: d is % BASE in encode()
-            // Make sure to adjust this function if you change any of the constants!
-            _unreachable!()
-        }
-    }
+    // SAFETY: Caller upholds the invariants.
+    unsafe { PUNYCODE.decode_unchecked(input) }
 }
 
 #[cfg(test)]
@@ -416,4 +260,43 @@ mod tests {
 
         Ok(())
     }
+
+    #[rstest]
+    #[case::czech("Pročprostěnemluvíčesky", "Proprostnemluvesky-uyb24dma41a")]
+    #[case::kinpachi("3年B組金八先生", "3B-ww4c5e180e575a65lsy2b")]
+    #[case::empty("", "")]
+    #[case::emoji("🦀", "zs9h")]
+    fn test_utf16_utf32(#[case] decoded: &str, #[case] encoded: &str) {
+        let decoded16 = decoded.encode_utf16().collect::<Vec<_>>();
+        let encoded16 = encoded.encode_utf16().collect::<Vec<_>>();
+        assert_eq!(encode_utf16(&decoded16), Ok(encoded16.clone()));
+        assert_eq!(decode_utf16(&encoded16), Ok(decoded16));
+
+        let decoded32 = decoded.chars().collect::<Vec<_>>();
+        let encoded32 = encoded.chars().collect::<Vec<_>>();
+        assert_eq!(encode_utf32(&decoded32), Ok(encoded32.clone()));
+        assert_eq!(decode_utf32(&encoded32), Ok(decoded32));
+    }
+
+    #[rstest]
+    #[case::czech(b"Proprostnemluvesky-uyb24dma41a", Ok("Pročprostěnemluvíčesky"))]
+    #[case::emoji(b"zs9h", Ok("🦀"))]
+    #[case::empty(b"", Ok(""))]
+    #[case::non_ascii("zs9ü".as_bytes(), Err(PunyDecodeError::NonAscii))]
+    #[case::invalid_utf8(b"zs\xff9h", Err(PunyDecodeError::NonAscii))]
+    fn test_decode_bytes(#[case] input: &[u8], #[case] expected: Result<&str, PunyDecodeError>) {
+        assert_eq!(decode_bytes(input).as_deref(), expected.as_deref());
+    }
+
+    #[test]
+    fn test_unpaired_surrogate() {
+        assert_eq!(
+            encode_utf16(&[0x61, 0xD83E]),
+            Err(PunyEncodeError::UnpairedSurrogate(0xD83E))
+        );
+        assert_eq!(
+            decode_utf16(&[0xDD80, 0x61]),
+            Err(PunyDecodeError::UnpairedSurrogate(0xDD80))
+        );
+    }
 }
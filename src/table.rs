@@ -0,0 +1,480 @@
+//! Sources of UTS #46 mapping data.
+//!
+//! By default, characters are looked up in the [`Builtin`] table, which is generated from the
+//! bundled `IdnaMappingTable.txt` at compile time. A [`MappingTable`] can be parsed from a newer
+//! version of that file at runtime and used in its place, see [`crate::Processor`].
+
+use core::{iter::FusedIterator, ops::RangeInclusive, str::FromStr};
+
+extern crate alloc;
+use alloc::vec::Vec;
+
+use crate::{
+    info::ascii_mapping,
+    parse::{self, Age, IdnaMap, ParseError},
+    Mapping, ToUnicodeError,
+};
+
+/// A lookup table from non-ASCII characters to their [`Mapping`].
+///
+/// ASCII is handled by the processing functions themselves and never looked up.
+pub trait Table {
+    /// Returns the range containing `c`, its mapping, and whether the output of the mapping is
+    /// inert under NFC.
+    ///
+    /// Characters without an entry are treated as [`Mapping::Disallowed`], and
+    /// [`Mapping::Deviation`] is treated as [`Mapping::Valid`].
+    ///
+    /// # Correctness
+    ///
+    /// The output is inert if every character it consists of is NFC on its own and never
+    /// interacts with its neighbours during normalization. Labels made of inert output only are
+    /// not checked for normalization, so returning `true` for output which is not inert leads to
+    /// unnormalized results. Returning `false` is always correct, only slower, so implementations
+    /// which can not tell should return `false`. [`MappingTable`] computes the flag itself.
+    fn entry(&self, c: char) -> Option<(RangeInclusive<char>, Mapping<'_>, bool)>;
+
+    /// Returns the mapping of `c`.
+    fn mapping(&self, c: char) -> Option<Mapping<'_>> {
+        self.entry(c).map(|(_, mapping, _)| mapping)
+    }
+
+    /// Returns the version of Unicode which introduced `c` as `(major, minor)`, or `None` if it
+    /// is unassigned.
+    ///
+    /// This is only used by [`crate::Processor::with_max_age`], which treats characters without
+    /// an age as too new. Tables without age data of their own can return the age known to
+    /// [`Builtin`].
+    fn age(&self, c: char) -> Option<(u8, u8)>;
+
+    /// Returns an iterator over the whole table, see [`Entries`].
+    ///
+    /// ASCII always comes from the built-in rules, even if the table has entries for it, since
+    /// ASCII is never looked up while processing.
+    fn entries(&self) -> Entries<'_, Self> {
+        Entries {
+            table: self,
+            next: Some('\0'),
+        }
+    }
+}
+
+impl<T: Table + ?Sized> Table for &T {
+    #[inline]
+    fn entry(&self, c: char) -> Option<(RangeInclusive<char>, Mapping<'_>, bool)> {
+        (**self).entry(c)
+    }
+
+    #[inline]
+    fn age(&self, c: char) -> Option<(u8, u8)> {
+        (**self).age(c)
+    }
+}
+
+/// Overrides the mapping of individual characters, see [`crate::Processor::with_policy`].
+///
+/// A policy can disallow characters which are valid, allow characters which are not, or map
+/// characters differently. Like a [`Table`], it is only consulted for non-ASCII characters, after
+/// they have been looked up in the table.
+///
+/// # Examples
+///
+/// ```
+/// use edna::{CharPolicy, Mapping, Processor, ToAsciiError};
+///
+/// /// Disallows emoji.
+/// struct NoEmoji;
+///
+/// impl CharPolicy for NoEmoji {
+///     fn apply<'a>(&'a self, c: char, mapping: Mapping<'a>) -> Mapping<'a> {
+///         match c {
+///             '\u{1F300}'..='\u{1FAFF}' => Mapping::Disallowed,
+///             _ => mapping,
+///         }
+///     }
+/// }
+///
+/// let processor = Processor::new().with_policy(NoEmoji);
+/// assert_eq!(processor.to_ascii("bücher.de").as_deref(), Ok("xn--bcher-kva.de"));
+/// assert_eq!(
+///     processor.to_ascii("🦀.rs"),
+///     Err(ToAsciiError::InvalidCharacter(Some('🦀')))
+/// );
+/// ```
+pub trait CharPolicy {
+    /// Whether this policy may change any mapping.
+    ///
+    /// Processors skip policies which set this to `false`, so [`NoPolicy`] costs nothing.
+    const ACTIVE: bool = true;
+
+    /// Returns the mapping to use for `c`, given its `mapping` in the table.
+    ///
+    /// Characters without an entry in the table are passed as [`Mapping::Disallowed`], and
+    /// [`Mapping::Deviation`] is passed as [`Mapping::Valid`].
+    fn apply<'a>(&'a self, c: char, mapping: Mapping<'a>) -> Mapping<'a>;
+}
+
+impl<P: CharPolicy + ?Sized> CharPolicy for &P {
+    const ACTIVE: bool = P::ACTIVE;
+
+    #[inline]
+    fn apply<'a>(&'a self, c: char, mapping: Mapping<'a>) -> Mapping<'a> {
+        (**self).apply(c, mapping)
+    }
+}
+
+/// The policy which keeps every mapping as it is, used by default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoPolicy;
+
+impl CharPolicy for NoPolicy {
+    const ACTIVE: bool = false;
+
+    #[inline]
+    fn apply<'a>(&'a self, _: char, mapping: Mapping<'a>) -> Mapping<'a> {
+        mapping
+    }
+}
+
+/// Looks up `c` in `table`, resolving the cases described in [`Table::entry`].
+#[inline]
+pub(crate) fn lookup<T: Table + ?Sized>(
+    table: &T,
+    c: char,
+) -> (RangeInclusive<char>, Mapping<'_>, bool) {
+    match table.entry(c) {
+        Some((range, Mapping::Deviation, nfc_inert)) => (range, Mapping::Valid, nfc_inert),
+        Some(entry) => entry,
+        None => (c..=c, Mapping::Disallowed, true),
+    }
+}
+
+/// A [`Table`] which may disallow characters for reasons other than their mapping.
+pub(crate) trait Explain: Table {
+    /// Returns the error to report for `c`, which [`lookup`] resolves to
+    /// [`Mapping::Disallowed`].
+    fn disallowed(&self, c: char) -> ToUnicodeError {
+        ToUnicodeError::InvalidCharacter(Some(c))
+    }
+}
+
+impl Explain for Builtin {}
+
+/// An iterator over the entries of a [`Table`], created by [`Table::entries`].
+///
+/// Every code point is covered by exactly one entry, in order. Neighbouring ranges with the same
+/// mapping are merged, and entries are resolved as described in [`Table::entry`].
+///
+/// ASCII is included as well, as it is processed rather than as the table has it: uppercase
+/// letters are mapped to lowercase, and everything else is valid, since the STD3 rules are not
+/// supported.
+///
+/// # Examples
+///
+/// ```
+/// use edna::{table::Stats, Mapping, Table};
+///
+/// let mut entries = edna::table::Builtin.entries();
+/// assert_eq!(entries.next(), Some(('\0'..='@', Mapping::Valid)));
+/// assert_eq!(entries.next(), Some(('A'..='A', Mapping::Mapped("a"))));
+///
+/// let stats: Stats = edna::table::Builtin.entries().collect();
+/// assert_eq!(stats.total(), 0x110000 - 0x800);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Entries<'a, T: ?Sized> {
+    table: &'a T,
+    next: Option<char>,
+}
+
+impl<'a, T: Table + ?Sized> Entries<'a, T> {
+    /// Returns the mapping of `c`, and the range following `c` which shares it.
+    fn entry(&self, c: char) -> (char, Mapping<'a>) {
+        let table: &'a T = self.table;
+        match c {
+            '\0'..='@' => ('@', Mapping::Valid),
+            'A'..='Z' => (c, ascii_mapping(c)),
+            '['..='\x7F' => ('\x7F', Mapping::Valid),
+            _ => {
+                let (range, mapping, _) = lookup(table, c);
+                (*range.end(), mapping)
+            }
+        }
+    }
+}
+
+impl<'a, T: Table + ?Sized> Iterator for Entries<'a, T> {
+    type Item = (RangeInclusive<char>, Mapping<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.next?;
+        let (mut end, mapping) = self.entry(start);
+
+        self.next = successor(end);
+        while let Some(next) = self.next {
+            let (next_end, next_mapping) = self.entry(next);
+            if next_mapping != mapping {
+                break;
+            }
+            end = next_end;
+            self.next = successor(end);
+        }
+
+        Some((start..=end, mapping))
+    }
+}
+
+impl<T: Table + ?Sized> FusedIterator for Entries<'_, T> {}
+
+/// Returns the code point after `c`, skipping surrogates.
+fn successor(c: char) -> Option<char> {
+    (c..=char::MAX).nth(1)
+}
+
+/// The number of code points with each status, collected from [`Entries`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Stats {
+    /// Code points which are valid, including deviations.
+    pub valid: u32,
+    /// Code points which are removed.
+    pub ignored: u32,
+    /// Code points which are replaced by other code points.
+    pub mapped: u32,
+    /// Code points which are disallowed, including unassigned ones.
+    pub disallowed: u32,
+}
+
+impl Stats {
+    /// Returns the number of code points counted, excluding surrogates.
+    #[must_use]
+    pub const fn total(&self) -> u32 {
+        self.valid + self.ignored + self.mapped + self.disallowed
+    }
+}
+
+impl<'a> FromIterator<(RangeInclusive<char>, Mapping<'a>)> for Stats {
+    fn from_iter<I: IntoIterator<Item = (RangeInclusive<char>, Mapping<'a>)>>(iter: I) -> Self {
+        const SURROGATES: RangeInclusive<u32> = 0xD800..=0xDFFF;
+
+        let mut stats = Self::default();
+        for (range, mapping) in iter {
+            let (start, end) = (*range.start() as u32, *range.end() as u32);
+            let mut len = end - start + 1;
+            if start < *SURROGATES.start() && end > *SURROGATES.end() {
+                len -= SURROGATES.end() - SURROGATES.start() + 1;
+            }
+
+            *match mapping {
+                Mapping::Valid | Mapping::Deviation => &mut stats.valid,
+                Mapping::Ignored => &mut stats.ignored,
+                Mapping::Mapped(_) => &mut stats.mapped,
+                Mapping::Disallowed => &mut stats.disallowed,
+            } += len;
+        }
+        stats
+    }
+}
+
+/// The mapping table compiled into the crate.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Builtin;
+
+impl Table for Builtin {
+    #[inline]
+    fn entry(&self, c: char) -> Option<(RangeInclusive<char>, Mapping<'_>, bool)> {
+        Mapping::entry(c)
+    }
+
+    fn age(&self, c: char) -> Option<(u8, u8)> {
+        crate::info::age(c)
+    }
+}
+
+/// A mapping table parsed at runtime from the `IdnaMappingTable.txt` format.
+///
+/// This allows picking up a new version of Unicode without recompiling. The file is processed
+/// exactly like the bundled one is at compile time.
+///
+/// # Examples
+///
+/// ```
+/// use edna::{MappingTable, Processor};
+///
+/// let table: MappingTable = "\
+/// 00C0..00C3    ; mapped                 ; 00E0          # 1.1  LATIN CAPITAL LETTER A WITH GRAVE..
+/// 00E0..00FF    ; valid                                  # 1.1  LATIN SMALL LETTER A WITH GRAVE..
+/// "
+/// .parse()?;
+///
+/// let processor = Processor::with_table(&table);
+/// assert_eq!(processor.to_unicode("Ábc.de").as_deref(), Ok("àbc.de"));
+/// assert!(processor.to_ascii("αβγ.de").is_err());
+/// # Ok::<(), edna::ParseError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappingTable {
+    ranges: Vec<IdnaMap>,
+    /// The first code point of every run of code points with the same age, and that age.
+    ages: Vec<(char, Option<Age>)>,
+    version: Option<(u8, u8, u8)>,
+}
+
+impl MappingTable {
+    /// Parses a mapping table in the `IdnaMappingTable.txt` format.
+    ///
+    /// Ranges which are not made of valid `char`s, i.e. surrogates, are skipped.
+    ///
+    /// # Errors
+    ///
+    /// A line is malformed or has an unknown status.
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        let lines = parse::read_lines(s.lines(), |_, _| ())?;
+        let ages = parse::ages(&lines);
+        let ranges = parse::ranges(lines);
+        let version = parse::version(s.lines());
+        Ok(Self {
+            ranges,
+            ages,
+            version,
+        })
+    }
+
+    /// Returns the Unicode version stated in the header of the table, if any.
+    ///
+    /// Compare with [`crate::NORMALIZATION_UNICODE_VERSION`]: characters assigned after that
+    /// version are not normalized correctly.
+    #[must_use]
+    pub const fn unicode_version(&self) -> Option<(u8, u8, u8)> {
+        self.version
+    }
+
+    /// Returns the number of ranges in the table.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Returns `true` if the table has no entries.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+impl FromStr for MappingTable {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Table for MappingTable {
+    fn entry(&self, c: char) -> Option<(RangeInclusive<char>, Mapping<'_>, bool)> {
+        let i = self.ranges.partition_point(|x| x.end < c);
+        self.ranges
+            .get(i)
+            .filter(|x| x.start <= c)
+            .map(|x| (x.start..=x.end, x.mapping(), x.nfc_inert))
+    }
+
+    fn age(&self, c: char) -> Option<(u8, u8)> {
+        let i = self.ages.partition_point(|&(start, _)| start <= c);
+        self.ages.get(i.checked_sub(1)?)?.1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use rstest::rstest;
+
+    use super::*;
+
+    const TABLE: &str = include_str!("../IdnaMappingTable.txt");
+
+    #[test]
+    fn test_builtin() {
+        let table = MappingTable::parse(TABLE).unwrap();
+        for c in '\u{80}'..=char::MAX {
+            assert_eq!(table.entry(c), Builtin.entry(c), "{c:?}");
+            assert_eq!(table.age(c), Builtin.age(c), "{c:?}");
+        }
+        assert_eq!(table.entry('a'), None);
+        assert_eq!(table.unicode_version(), Some(crate::UNICODE_VERSION));
+    }
+
+    #[test]
+    fn test_entries() {
+        let entries = Builtin.entries().collect::<std::vec::Vec<_>>();
+        let runtime = MappingTable::parse(TABLE).unwrap();
+        assert!(runtime.entries().eq(entries.iter().cloned()));
+
+        let mut next = Some('\0');
+        for (range, mapping) in &entries {
+            assert_eq!(Some(*range.start()), next);
+            next = successor(*range.end());
+            for c in [*range.start(), *range.end()] {
+                assert_eq!(crate::char_info(c).mapping, *mapping);
+            }
+        }
+        assert_eq!(next, None);
+
+        let stats = entries.into_iter().collect::<Stats>();
+        assert_eq!(
+            stats,
+            Stats {
+                valid: 148_195,
+                ignored: 294,
+                mapped: 6348,
+                disallowed: 957_227,
+            }
+        );
+    }
+
+    #[rstest]
+    #[case::missing_status("00C0", ParseError::MissingField { line: 1 })]
+    #[case::bad_start(
+        "00G0 ; valid",
+        ParseError::InvalidCodePoint { line: 1, value: "00G0".into() }
+    )]
+    #[case::bad_mapping(
+        "# comment\n\n00C0 ; mapped ; D800",
+        ParseError::InvalidCodePoint { line: 3, value: "D800".into() }
+    )]
+    #[case::status(
+        "00C0 ; weird",
+        ParseError::UnknownStatus { line: 1, status: "weird".into() }
+    )]
+    fn test_parse_error(#[case] input: &str, #[case] expected: ParseError) {
+        assert_eq!(MappingTable::parse(input), Err(expected));
+    }
+
+    #[test]
+    fn test_parse() {
+        let table = MappingTable::parse(
+            "0041..005A ; mapped ; 0061\n\
+             D800..DFFF ; disallowed\n\
+             00C0 ; mapped ; 00E0 # comment\n\
+             00C1 ; disallowed_STD3_mapped ; 00E1\n\
+             00E0..00E1 ; valid\n\
+             00E2 ; deviation\n",
+        )
+        .unwrap();
+
+        assert_eq!(table.len(), 4);
+        assert_eq!(table.unicode_version(), None);
+        assert_eq!(table.mapping('A'), None);
+        assert_eq!(table.mapping('À'), Some(Mapping::Mapped("à")));
+        assert_eq!(table.mapping('Á'), Some(Mapping::Mapped("á")));
+        assert_eq!(table.entry('à'), Some(('à'..='á', Mapping::Valid, true)));
+        assert_eq!(table.mapping('â'), Some(Mapping::Valid));
+        assert_eq!(table.mapping('ã'), None);
+
+        // ASCII comes from the built-in rules
+        let mut entries = table.entries();
+        assert_eq!(entries.next(), Some(('\0'..='@', Mapping::Valid)));
+        assert_eq!(entries.next(), Some(('A'..='A', Mapping::Mapped("a"))));
+    }
+}
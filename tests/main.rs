@@ -34,6 +34,22 @@ fn to_ascii_compare_to_idna(#[case] input: &str) {
     assert_eq!(got.unwrap(), expected.unwrap());
 }
 
+#[rstest]
+#[case::munchen("xn--mnchen-3ya.de")]
+#[case::uber("www.xn--ber-goa.com")]
+#[case::chinese_simplified("xn--fsqu00a.xn--0zwm56d")]
+#[case::greek("xn--hxajbheg2az3al.xn--jxalpdlp")]
+#[case::emoji_middle("xn--example-wg05f.com")]
+#[case::emoji("xn--zs9h.xn--53h")]
+#[case::mixed("Bücher.xn--fa-hia.de")]
+fn to_unicode_compare_to_idna(#[case] input: &str) {
+    let got = edna::to_unicode(input);
+    let (expected, result) =
+        UTS46.to_unicode(input.as_bytes(), AsciiDenyList::EMPTY, Hyphens::Allow);
+    assert!(result.is_ok());
+    assert_eq!(got.unwrap(), expected);
+}
+
 /*
 #[rstest]
 #[case::egyptian("egbpdaj6bu4bxfgehfvwxn")]
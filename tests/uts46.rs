@@ -37,7 +37,7 @@ enum ReadLineError {
     #[error("invalid char: {0:X}")]
     InvalidChar(u32),
     #[error("not yet implemented: {0}")]
-    NYI(String),
+    Nyi(String),
 }
 
 impl From<u32> for ReadLineError {
@@ -75,7 +75,7 @@ fn read_line(n: usize, line: &str) -> Result<Uts46, ReadLineError> {
 
     let mut to_unicode = iter.next().unwrap().to_string();
     unescape(&mut to_unicode)?;
-    if to_unicode == "" {
+    if to_unicode.is_empty() {
         to_unicode = input.clone();
     }
 
@@ -90,8 +90,8 @@ fn read_line(n: usize, line: &str) -> Result<Uts46, ReadLineError> {
             Some('C') => return Err(ReadLineError::Context(input)),
             // Processing step n
             Some('P') => (),
-            Some('U') => return Err(ReadLineError::NYI("Use STD3ASCIIRules".into())),
-            //Some('V') => return Err(ReadLineError::NYI("Validity".into())),
+            Some('U') => return Err(ReadLineError::Nyi("Use STD3ASCIIRules".into())),
+            //Some('V') => return Err(ReadLineError::Nyi("Validity".into())),
             Some('V') => (),
             // toUnicode issues
             Some('X') => (),
@@ -104,7 +104,7 @@ fn read_line(n: usize, line: &str) -> Result<Uts46, ReadLineError> {
 
     let mut to_ascii = iter.next().unwrap().to_string();
     unescape(&mut to_ascii)?;
-    if to_ascii == "" {
+    if to_ascii.is_empty() {
         to_ascii = input.clone();
     }
 
@@ -136,7 +136,7 @@ fn uts46() -> Vec<Uts46> {
 
 #[rstest]
 #[ignore]
-fn test_to_ascii(uts46: &Vec<Uts46>) {
+fn test_to_ascii(uts46: &[Uts46]) {
     for x in uts46.iter() {
         let input = &x.input;
         let expected = &x.to_ascii;
@@ -153,3 +153,23 @@ fn test_to_ascii(uts46: &Vec<Uts46>) {
         );
     }
 }
+
+#[rstest]
+#[ignore]
+fn test_to_unicode(uts46: &[Uts46]) {
+    for x in uts46.iter() {
+        let input = &x.input;
+        let expected = &x.to_unicode;
+        let got = edna::to_unicode(input).unwrap();
+        let n = &x.line;
+        assert_eq!(
+            &got,
+            expected,
+            "failed line {n} ({})",
+            input
+                .chars()
+                .map(|x| format!("\\u{:0>4X}", x as u32))
+                .collect::<String>()
+        );
+    }
+}